@@ -0,0 +1,56 @@
+use std::f64::consts::PI;
+
+use crate::knee_clip;
+
+/// How far the signal is offset before asymmetric clipping, relative to the threshold. The
+/// positive half starts clipping at half the threshold while the negative half has 1.5 times the
+/// headroom.
+const ASYMMETRY_BIAS: f32 = 0.5;
+/// The DC blocker's cutoff frequency.
+const DC_BLOCKER_CUTOFF_HZ: f64 = 10.0;
+
+/// Clip the signal unevenly around zero. Symmetric clipping only adds odd harmonics, offsetting
+/// the signal first makes the two halves clip differently, which adds even harmonics. The static
+/// offset is removed again, but the clipping itself still leaves some DC behind that needs to be
+/// filtered out with a [`DcBlocker`]. The knee works the same as with the regular clipper.
+pub fn asymmetric_clip(input: f32, threshold: f32, knee: f32) -> f32 {
+    let bias = threshold * ASYMMETRY_BIAS;
+
+    knee_clip(input + bias, threshold, knee) - bias
+}
+
+/// A one pole high-pass filter that removes DC offsets. Like [`Biquad`][crate::biquad::Biquad],
+/// this can run in either single or double precision.
+#[derive(Debug, Default, Clone)]
+pub struct DcBlocker {
+    coeff: f64,
+
+    previous_input: f32,
+    previous_output: f64,
+}
+
+impl DcBlocker {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            coeff: 1.0 - (2.0 * PI * DC_BLOCKER_CUTOFF_HZ / sample_rate as f64),
+            ..Self::default()
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.previous_input = 0.0;
+        self.previous_output = 0.0;
+    }
+
+    pub fn process(&mut self, input: f32, double_precision: bool) -> f32 {
+        let output = if double_precision {
+            (input - self.previous_input) as f64 + self.coeff * self.previous_output
+        } else {
+            (input - self.previous_input + self.coeff as f32 * self.previous_output as f32) as f64
+        };
+        self.previous_input = input;
+        self.previous_output = output;
+
+        output as f32
+    }
+}
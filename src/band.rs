@@ -0,0 +1,14 @@
+use nih_plug::prelude::Enum;
+
+/// Lets the selected band be heard on its own in band mode, to make finding the right frequency
+/// region easier. This is a tuning aid, the solo replaces the whole output.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BandSolo {
+    Off,
+    /// The band before it's distorted.
+    #[name = "Pre"]
+    PreDistortion,
+    /// The band after it's distorted.
+    #[name = "Post"]
+    PostDistortion,
+}
@@ -0,0 +1,261 @@
+use std::f64::consts::{FRAC_1_SQRT_2, LN_2, PI};
+
+/// Normalized biquad filter coefficients, with `a0` divided out. See the Audio EQ Cookbook for
+/// the formulas. These are always computed in double precision.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiquadCoefficients {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+impl Default for BiquadCoefficients {
+    /// A filter that passes the signal through unchanged.
+    fn default() -> Self {
+        Self {
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+        }
+    }
+}
+
+impl BiquadCoefficients {
+    /// A band-pass filter with a 0 dB peak at `frequency`. The bandwidth is measured in octaves
+    /// between the -3 dB points.
+    pub fn bandpass(sample_rate: f32, frequency: f32, bandwidth: f32) -> Self {
+        let sample_rate = sample_rate as f64;
+        let omega = 2.0 * PI * nyquist_clamp(sample_rate, frequency as f64) / sample_rate;
+        let (sin_omega, cos_omega) = omega.sin_cos();
+        let alpha = sin_omega * (LN_2 / 2.0 * bandwidth as f64 * omega / sin_omega).sinh();
+
+        Self::normalized(
+            alpha,
+            0.0,
+            -alpha,
+            1.0 + alpha,
+            -2.0 * cos_omega,
+            1.0 - alpha,
+        )
+    }
+
+    /// A second order Butterworth low-pass filter.
+    pub fn lowpass(sample_rate: f32, frequency: f32) -> Self {
+        let (alpha, cos_omega) = butterworth_terms(sample_rate, frequency);
+
+        Self::normalized(
+            (1.0 - cos_omega) / 2.0,
+            1.0 - cos_omega,
+            (1.0 - cos_omega) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos_omega,
+            1.0 - alpha,
+        )
+    }
+
+    /// A second order Butterworth high-pass filter.
+    pub fn highpass(sample_rate: f32, frequency: f32) -> Self {
+        Self::highpass_with_q(sample_rate, frequency, FRAC_1_SQRT_2)
+    }
+
+    /// A second order high-pass filter with a custom Q, for building higher order filters out of
+    /// multiple sections.
+    pub fn highpass_with_q(sample_rate: f32, frequency: f32, q: f64) -> Self {
+        let (alpha, cos_omega) = filter_terms(sample_rate, frequency, q);
+
+        Self::normalized(
+            (1.0 + cos_omega) / 2.0,
+            -(1.0 + cos_omega),
+            (1.0 + cos_omega) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos_omega,
+            1.0 - alpha,
+        )
+    }
+
+    /// A first order high-pass filter, using the bilinear transform.
+    pub fn highpass_first_order(sample_rate: f32, frequency: f32) -> Self {
+        let sample_rate = sample_rate as f64;
+        let k = (PI * nyquist_clamp(sample_rate, frequency as f64) / sample_rate).tan();
+
+        Self::normalized(1.0, -1.0, 0.0, 1.0 + k, k - 1.0, 0.0)
+    }
+
+    /// A first order high shelf with a gain of `gain` (linear) above `frequency` and unity gain
+    /// below it, using the bilinear transform. The shelf is halfway up at `frequency` on a
+    /// decibel scale. This is minimum phase, so it can be inverted with
+    /// [`inverse()`][Self::inverse()].
+    pub fn high_shelf_first_order(sample_rate: f32, frequency: f32, gain: f32) -> Self {
+        let sample_rate = sample_rate as f64;
+        let gain = gain as f64;
+        // The shelf's pole sits `sqrt(gain)` above the pivot and its zero the same distance below,
+        // which puts the pivot exactly halfway between them after the frequency warping
+        let pivot = (PI * nyquist_clamp(sample_rate, frequency as f64) / sample_rate).tan();
+        let k = pivot * gain.sqrt();
+
+        Self::normalized(gain + k, k - gain, 0.0, 1.0 + k, k - 1.0, 0.0)
+    }
+
+    /// The filter that exactly undoes this one. This only works for minimum phase filters, or the
+    /// inverse becomes unstable.
+    pub fn inverse(self) -> Self {
+        Self::normalized(1.0, self.a1, self.a2, self.b0, self.b1, self.b2)
+    }
+
+    fn normalized(b0: f64, b1: f64, b2: f64, a0: f64, a1: f64, a2: f64) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+}
+
+/// A single biquad filter in transposed direct form II. The filter can run in either single or
+/// double precision. The state is stored as doubles either way so the precision can be switched
+/// while audio is running, but in single precision mode it's rounded to `f32` on every sample,
+/// which gives the same results as storing it as `f32`.
+#[derive(Debug, Default, Clone)]
+pub struct Biquad {
+    coefficients: BiquadCoefficients,
+
+    s1: f64,
+    s2: f64,
+}
+
+impl Biquad {
+    /// Change the filter's coefficients. This keeps the filter's state so the coefficients can be
+    /// changed while audio is running.
+    pub fn set_coefficients(&mut self, coefficients: BiquadCoefficients) {
+        self.coefficients = coefficients;
+    }
+
+    pub fn reset(&mut self) {
+        self.s1 = 0.0;
+        self.s2 = 0.0;
+    }
+
+    pub fn process(&mut self, input: f32, double_precision: bool) -> f32 {
+        let BiquadCoefficients { b0, b1, b2, a1, a2 } = self.coefficients;
+        if double_precision {
+            let input = input as f64;
+            let output = b0 * input + self.s1;
+            self.s1 = b1 * input - a1 * output + self.s2;
+            self.s2 = b2 * input - a2 * output;
+
+            output as f32
+        } else {
+            let (b0, b1, b2, a1, a2) = (b0 as f32, b1 as f32, b2 as f32, a1 as f32, a2 as f32);
+            let (s1, s2) = (self.s1 as f32, self.s2 as f32);
+            let output = b0 * input + s1;
+            self.s1 = (b1 * input - a1 * output + s2) as f64;
+            self.s2 = (b2 * input - a2 * output) as f64;
+
+            output
+        }
+    }
+}
+
+/// The `alpha` and `cos(omega)` terms shared by the low-pass and high-pass filters, using a Q of
+/// `1 / sqrt(2)` for a maximally flat passband.
+fn butterworth_terms(sample_rate: f32, frequency: f32) -> (f64, f64) {
+    filter_terms(sample_rate, frequency, FRAC_1_SQRT_2)
+}
+
+/// The `alpha` and `cos(omega)` terms for a filter with the given Q.
+fn filter_terms(sample_rate: f32, frequency: f32, q: f64) -> (f64, f64) {
+    let sample_rate = sample_rate as f64;
+    let omega = 2.0 * PI * nyquist_clamp(sample_rate, frequency as f64) / sample_rate;
+    let (sin_omega, cos_omega) = omega.sin_cos();
+
+    (sin_omega / (2.0 * q), cos_omega)
+}
+
+/// Keep filter frequencies safely below the Nyquist frequency, where the formulas break down.
+fn nyquist_clamp(sample_rate: f64, frequency: f64) -> f64 {
+    frequency.clamp(1.0, sample_rate * 0.49)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run a unit step through a filter and return how far the settled output is from the
+    /// filter's DC gain of 1.
+    fn step_error(coefficients: BiquadCoefficients, double_precision: bool) -> f32 {
+        let mut filter = Biquad::default();
+        filter.set_coefficients(coefficients);
+        let output = (0..200_000).fold(0.0, |_, _| filter.process(1.0, double_precision));
+
+        (output - 1.0).abs()
+    }
+
+    /// The filter's magnitude response at `frequency`, in decibels.
+    fn magnitude_db(coefficients: BiquadCoefficients, sample_rate: f32, frequency: f32) -> f64 {
+        let BiquadCoefficients { b0, b1, b2, a1, a2 } = coefficients;
+        let omega = 2.0 * PI * frequency as f64 / sample_rate as f64;
+        // Evaluate both polynomials at `z^-1 = e^(-j omega)`
+        let polynomial_magnitude = |c0: f64, c1: f64, c2: f64| {
+            let re = c0 + c1 * omega.cos() + c2 * (2.0 * omega).cos();
+            let im = -c1 * omega.sin() - c2 * (2.0 * omega).sin();
+            (re * re + im * im).sqrt()
+        };
+
+        20.0 * (polynomial_magnitude(b0, b1, b2) / polynomial_magnitude(1.0, a1, a2)).log10()
+    }
+
+    #[test]
+    fn high_shelf_is_halfway_up_at_its_frequency() {
+        let sample_rate = 48_000.0;
+        for (frequency, gain_db) in [(2000.0, 18.0), (500.0, 6.0), (8000.0, 12.0)] {
+            let gain = 10.0f32.powf(gain_db / 20.0);
+            let shelf = BiquadCoefficients::high_shelf_first_order(sample_rate, frequency, gain);
+
+            let pivot_db = magnitude_db(shelf, sample_rate, frequency);
+            assert!(
+                (pivot_db - gain_db as f64 / 2.0).abs() < 0.1,
+                "{pivot_db} dB at {frequency} Hz for a {gain_db} dB shelf"
+            );
+            assert!(magnitude_db(shelf, sample_rate, 10.0).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn high_shelf_and_its_inverse_are_flat() {
+        let sample_rate = 48_000.0;
+        let shelf = BiquadCoefficients::high_shelf_first_order(sample_rate, 2000.0, 7.943);
+        let (mut emphasis, mut de_emphasis) = (Biquad::default(), Biquad::default());
+        emphasis.set_coefficients(shelf);
+        de_emphasis.set_coefficients(shelf.inverse());
+
+        for frequency in [50.0, 2000.0, 15_000.0] {
+            let error = (0..48_000)
+                .map(|i| {
+                    let input = (2.0 * PI * frequency * i as f64 / sample_rate as f64).sin() as f32;
+                    let output = de_emphasis.process(emphasis.process(input, true), true);
+                    (output - input).abs()
+                })
+                .fold(0.0f32, f32::max);
+            assert!(error < 1e-4, "error {error} at {frequency} Hz");
+        }
+    }
+
+    #[test]
+    fn double_precision_is_more_accurate_at_low_cutoffs() {
+        let coefficients = BiquadCoefficients::lowpass(96_000.0, 10.0);
+        let single_error = step_error(coefficients, false);
+        let double_error = step_error(coefficients, true);
+
+        assert!(double_error < 1e-6, "double precision error {double_error}");
+        assert!(
+            single_error > double_error * 10.0,
+            "single {single_error}, double {double_error}"
+        );
+    }
+}
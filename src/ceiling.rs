@@ -0,0 +1,44 @@
+use crate::envelope::EnvelopeFollower;
+
+/// How quickly the follower picks up new peaks.
+const CEILING_ATTACK_MS: f32 = 5.0;
+/// How slowly the follower falls back down when the signal gets quieter.
+const CEILING_RELEASE_MS: f32 = 2000.0;
+/// The lowest threshold the follower can set, the same as the threshold parameter's minimum.
+const MIN_THRESHOLD: f32 = 0.001;
+
+/// Follows the recent peak level of the driven signal so the threshold can sit a fixed distance
+/// below it. This makes the clipper bite about equally hard regardless of the input level.
+#[derive(Debug, Default, Clone)]
+pub struct CeilingFollower {
+    envelope_follower: EnvelopeFollower,
+    envelope: f32,
+}
+
+impl CeilingFollower {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            envelope_follower: EnvelopeFollower::new(
+                sample_rate,
+                CEILING_ATTACK_MS,
+                CEILING_RELEASE_MS,
+            ),
+            envelope: 0.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.envelope_follower.reset();
+        self.envelope = 0.0;
+    }
+
+    /// Add a frame's peak driven level. The threshold for the next frame is based on this.
+    pub fn add_frame(&mut self, driven_peak: f32) {
+        self.envelope = self.envelope_follower.process(driven_peak);
+    }
+
+    /// The threshold `offset` below the followed peak. `offset` is a linear gain below 1.
+    pub fn threshold(&self, offset: f32) -> f32 {
+        (self.envelope * offset).clamp(MIN_THRESHOLD, 1.0)
+    }
+}
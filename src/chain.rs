@@ -0,0 +1,199 @@
+use nih_plug::params::persist::PersistentField;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// The number of stages in the wet chain.
+pub const WET_STAGE_COUNT: usize = 3;
+/// The order the stages run in by default.
+const DEFAULT_WET_CHAIN: [WetStage; WET_STAGE_COUNT] =
+    [WetStage::Transient, WetStage::Eq, WetStage::Compressor];
+
+/// A reorderable stage on the wet path after the clipper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WetStage {
+    /// The transient clipping blend.
+    Transient,
+    /// The wet low and high cut filters.
+    Eq,
+    /// The wet compressor.
+    Compressor,
+}
+
+impl WetStage {
+    const ALL: [WetStage; WET_STAGE_COUNT] =
+        [WetStage::Transient, WetStage::Eq, WetStage::Compressor];
+
+    /// The name shown in the editor.
+    pub fn name(self) -> &'static str {
+        match self {
+            WetStage::Transient => "Transients",
+            WetStage::Eq => "EQ",
+            WetStage::Compressor => "Comp",
+        }
+    }
+
+    /// The stable identifier used in the saved state.
+    fn id(self) -> &'static str {
+        match self {
+            WetStage::Transient => "transient",
+            WetStage::Eq => "eq",
+            WetStage::Compressor => "compressor",
+        }
+    }
+
+    fn index(self) -> u8 {
+        WetStage::ALL
+            .iter()
+            .position(|&stage| stage == self)
+            .unwrap_or(0) as u8
+    }
+}
+
+/// The order of the wet path's stages, set from the editor and read on the audio thread. The
+/// whole order is packed into a single atomic so the audio thread never sees half of a change.
+/// It's persisted as a comma separated list of stage IDs, and anything that isn't a valid order
+/// is replaced with the default order.
+#[derive(Debug)]
+pub struct WetChain {
+    /// Two bits per stage, with the first stage in the lowest bits.
+    packed: AtomicU8,
+}
+
+impl Default for WetChain {
+    fn default() -> Self {
+        Self {
+            packed: AtomicU8::new(pack(DEFAULT_WET_CHAIN)),
+        }
+    }
+}
+
+impl<'a> PersistentField<'a, String> for WetChain {
+    fn set(&self, new_value: String) {
+        let stages: Vec<WetStage> = new_value
+            .split(',')
+            .filter_map(|id| WetStage::ALL.into_iter().find(|stage| stage.id() == id))
+            .collect();
+
+        let order = match <[WetStage; WET_STAGE_COUNT]>::try_from(stages) {
+            Ok(order) if is_valid(order) => order,
+            _ => DEFAULT_WET_CHAIN,
+        };
+        self.store(order);
+    }
+
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&String) -> R,
+    {
+        let ids: Vec<&str> = self.order().iter().map(|stage| stage.id()).collect();
+        f(&ids.join(","))
+    }
+}
+
+impl WetChain {
+    pub fn order(&self) -> [WetStage; WET_STAGE_COUNT] {
+        let packed = self.packed.load(Ordering::Relaxed);
+        let order = std::array::from_fn(|idx| {
+            let stage_idx = (packed >> (idx * 2)) & 0b11;
+            WetStage::ALL[(stage_idx as usize).min(WET_STAGE_COUNT - 1)]
+        });
+
+        if is_valid(order) {
+            order
+        } else {
+            DEFAULT_WET_CHAIN
+        }
+    }
+
+    /// Move the stage at `idx` one step earlier in the chain. The first stage moves to the end.
+    pub fn move_earlier(&self, idx: usize) {
+        let mut order = self.order();
+        if idx >= WET_STAGE_COUNT {
+            return;
+        }
+
+        if idx == 0 {
+            order.rotate_left(1);
+        } else {
+            order.swap(idx, idx - 1);
+        }
+        self.store(order);
+    }
+
+    fn store(&self, order: [WetStage; WET_STAGE_COUNT]) {
+        self.packed.store(pack(order), Ordering::Relaxed);
+    }
+}
+
+fn pack(order: [WetStage; WET_STAGE_COUNT]) -> u8 {
+    order.iter().enumerate().fold(0, |packed, (idx, stage)| {
+        packed | (stage.index() << (idx * 2))
+    })
+}
+
+/// Whether every stage appears exactly once.
+fn is_valid(order: [WetStage; WET_STAGE_COUNT]) -> bool {
+    WetStage::ALL
+        .iter()
+        .all(|stage| order.iter().filter(|&other| other == stage).count() == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The state string for a chain, the way it's persisted.
+    fn persisted(chain: &WetChain) -> String {
+        chain.map(|ids| ids.clone())
+    }
+
+    #[test]
+    fn set_restores_a_valid_order() {
+        let chain = WetChain::default();
+        chain.set(String::from("compressor,transient,eq"));
+        assert_eq!(
+            chain.order(),
+            [WetStage::Compressor, WetStage::Transient, WetStage::Eq]
+        );
+        assert_eq!(persisted(&chain), "compressor,transient,eq");
+    }
+
+    #[test]
+    fn set_replaces_invalid_orders_with_the_default() {
+        for invalid in [
+            // Duplicates, also when every stage is there
+            "eq,eq,compressor",
+            "transient,eq,compressor,eq",
+            // Missing stages
+            "eq,compressor",
+            "",
+            // Unknown stages
+            "transient,eq,reverb",
+            "transient,EQ,compressor",
+        ] {
+            let chain = WetChain::default();
+            chain.set(String::from("compressor,eq,transient"));
+            chain.set(String::from(invalid));
+            assert_eq!(chain.order(), DEFAULT_WET_CHAIN, "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn move_earlier_wraps_around() {
+        let chain = WetChain::default();
+        chain.move_earlier(2);
+        assert_eq!(
+            chain.order(),
+            [WetStage::Transient, WetStage::Compressor, WetStage::Eq]
+        );
+        chain.move_earlier(0);
+        assert_eq!(
+            chain.order(),
+            [WetStage::Compressor, WetStage::Eq, WetStage::Transient]
+        );
+        chain.move_earlier(WET_STAGE_COUNT);
+        assert_eq!(
+            chain.order(),
+            [WetStage::Compressor, WetStage::Eq, WetStage::Transient]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use nih_plug::prelude::{util, Smoother, SmoothingStyle};
+
+use crate::envelope::time_constant;
+
+/// How long the energy estimates are averaged over. Long enough to ignore individual cycles, short
+/// enough to follow a drive sweep.
+const AVERAGING_MS: f32 = 300.0;
+/// How long the compensation takes to fade in and out when it's toggled.
+const FADE_MS: f32 = 50.0;
+/// How loud the added harmonics count compared to the fundamental. The ear is more sensitive to
+/// the harmonics' higher frequencies, but they're also partly masked by the fundamental, so they
+/// get a bit less than full weight. This is what makes the compensation different from plain RMS
+/// matching, which would count every bit of added harmonic energy as loudness.
+const HARMONIC_WEIGHT: f32 = 0.5;
+/// The most the compensation will boost or cut the wet signal.
+const MAX_COMPENSATION_DB: f32 = 24.0;
+
+/// Keeps the wet signal's perceived level steady while the drive changes, so sweeping the drive
+/// changes the timbre instead of the loudness.
+///
+/// The estimate splits the wet signal's energy into two parts. The fundamental is the part that's
+/// correlated with the clean input, with gain `a = E[wet * clean] / E[clean^2]`, and its energy is
+/// `a^2 * E[clean^2]`. Everything else, `E[wet^2]` minus that, is the harmonic energy added by the
+/// clipper. The compensation gain then brings the fundamental's energy plus the weighted harmonic
+/// energy back to the clean signal's energy. All expectations are running averages over all
+/// channels, so the gain is shared and the stereo image stays put.
+#[derive(Debug)]
+pub struct DriveCompensation {
+    averaging_coeff: f32,
+
+    clean_power: f32,
+    wet_power: f32,
+    correlation: f32,
+
+    /// The gain for the next frame, computed from the averages at the end of the last frame.
+    gain: f32,
+
+    enabled: bool,
+    enabled_amount: Smoother<f32>,
+}
+
+impl Default for DriveCompensation {
+    fn default() -> Self {
+        Self {
+            averaging_coeff: 0.0,
+
+            clean_power: 0.0,
+            wet_power: 0.0,
+            correlation: 0.0,
+
+            gain: 1.0,
+
+            enabled: false,
+            enabled_amount: Smoother::new(SmoothingStyle::Linear(FADE_MS)),
+        }
+    }
+}
+
+impl DriveCompensation {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            averaging_coeff: time_constant(sample_rate, AVERAGING_MS),
+            ..Self::default()
+        }
+    }
+
+    pub fn reset(&mut self, enabled: bool) {
+        self.clean_power = 0.0;
+        self.wet_power = 0.0;
+        self.correlation = 0.0;
+        self.gain = 1.0;
+
+        self.enabled = enabled;
+        self.enabled_amount.reset(enabled as u8 as f32);
+    }
+
+    /// The gain to apply to this frame's wet signal. The estimates keep running while the
+    /// compensation is disabled, so it's already settled when it gets enabled.
+    pub fn next_gain(&mut self, sample_rate: f32, enabled: bool) -> f32 {
+        if enabled != self.enabled {
+            self.enabled = enabled;
+            self.enabled_amount
+                .set_target(sample_rate, enabled as u8 as f32);
+        }
+
+        1.0 + (self.gain - 1.0) * self.enabled_amount.next()
+    }
+
+    /// Add a frame's clean and uncompensated wet power, and their product, summed over all
+    /// channels.
+    pub fn add_frame(&mut self, clean_power: f32, wet_power: f32, correlation: f32) {
+        let coeff = self.averaging_coeff;
+        self.clean_power = clean_power + coeff * (self.clean_power - clean_power);
+        self.wet_power = wet_power + coeff * (self.wet_power - wet_power);
+        self.correlation = correlation + coeff * (self.correlation - correlation);
+
+        if self.clean_power <= util::MINUS_INFINITY_GAIN * util::MINUS_INFINITY_GAIN {
+            // There's nothing to go on during silence, so the last gain is kept
+            return;
+        }
+
+        let fundamental_gain = self.correlation / self.clean_power;
+        let fundamental_power = fundamental_gain * fundamental_gain * self.clean_power;
+        let harmonic_power = (self.wet_power - fundamental_power).max(0.0);
+        let perceived_power = fundamental_power + harmonic_power * HARMONIC_WEIGHT;
+        if perceived_power > 0.0 {
+            let max_gain = util::db_to_gain(MAX_COMPENSATION_DB);
+            self.gain = (self.clean_power / perceived_power)
+                .sqrt()
+                .clamp(max_gain.recip(), max_gain);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use nih_plug::prelude::util;
+
+use crate::envelope::EnvelopeFollower;
+
+/// A simple feed-forward compressor. In front of the clipper it evens out the level going into
+/// the clipper, and there the channels are linked: the compressor reacts to the loudest channel
+/// and applies the same gain to all of them, which keeps the stereo image stable. The wet
+/// compressor after the clipper uses one of these per channel instead.
+#[derive(Debug, Default, Clone)]
+pub struct Compressor {
+    envelope: EnvelopeFollower,
+    /// The attack and release times the envelope follower was last configured with.
+    times: (f32, f32),
+}
+
+impl Compressor {
+    /// Update the attack and release times. The coefficients are only recomputed when they
+    /// change.
+    pub fn set_times(&mut self, sample_rate: f32, attack_ms: f32, release_ms: f32) {
+        if (attack_ms, release_ms) != self.times {
+            self.envelope.set_times(sample_rate, attack_ms, release_ms);
+            self.times = (attack_ms, release_ms);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.envelope.reset();
+    }
+
+    /// Feed the frame's peak level, or a single sample, into the envelope and return the gain for
+    /// the frame, not including makeup gain. `threshold` is a linear gain value and `ratio` is the
+    /// usual `n:1`.
+    pub fn process(&mut self, peak: f32, threshold: f32, ratio: f32) -> f32 {
+        let envelope = self.envelope.process(peak);
+        if envelope <= threshold {
+            return 1.0;
+        }
+
+        let overshoot_db = util::gain_to_db(envelope) - util::gain_to_db(threshold);
+        util::db_to_gain(-overshoot_db * (1.0 - ratio.recip()))
+    }
+}
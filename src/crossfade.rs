@@ -0,0 +1,101 @@
+/// Crossfades from the output of a previous mode to the output of the current mode for a short
+/// time after the mode changes. Switching between transfer functions mid-playback would otherwise
+/// cause a discontinuity, and thus a click.
+#[derive(Debug, Clone)]
+pub struct ModeCrossfade<T> {
+    current: T,
+    previous: T,
+
+    length: usize,
+    remaining: usize,
+}
+
+impl<T: Copy + PartialEq> ModeCrossfade<T> {
+    pub fn new(mode: T, length: usize) -> Self {
+        Self {
+            current: mode,
+            previous: mode,
+
+            length,
+            remaining: 0,
+        }
+    }
+
+    /// Change the length of future crossfades in samples. A crossfade in progress is shortened if
+    /// it would now take longer than that. At zero, mode changes are instant.
+    pub fn set_length(&mut self, length: usize) {
+        self.length = length;
+        self.remaining = self.remaining.min(length);
+    }
+
+    /// Stop any crossfade in progress and jump straight to `mode`.
+    pub fn reset(&mut self, mode: T) {
+        self.current = mode;
+        self.previous = mode;
+        self.remaining = 0;
+    }
+
+    /// Advance the crossfade by one sample with the mode for that sample. While a crossfade is in
+    /// progress this returns the previous mode along with the weight its output should get. Both
+    /// modes then need to be evaluated and blended as `current + (previous - current) * weight`.
+    /// If the mode changes again mid-fade, the fade restarts from the mode that was active.
+    pub fn next(&mut self, mode: T) -> Option<(T, f32)> {
+        if mode != self.current {
+            self.previous = self.current;
+            self.current = mode;
+            self.remaining = self.length;
+        }
+
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let weight = self.remaining as f32 / (self.length + 1) as f32;
+        self.remaining -= 1;
+
+        Some((self.previous, weight))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two sines with different levels standing in for the outputs of two clipping modes.
+    fn mode_output(brickwall: bool, i: usize) -> f32 {
+        let sine = (i as f32 * 0.05).sin();
+        if brickwall {
+            sine
+        } else {
+            0.25 * sine
+        }
+    }
+
+    fn largest_step(length: usize) -> f32 {
+        let mut crossfade = ModeCrossfade::new(false, length);
+        let mut previous = 0.0;
+        let mut largest_step = 0.0f32;
+        for i in 0..2000 {
+            let mode = i >= 1000;
+            let mut output = mode_output(mode, i);
+            if let Some((previous_mode, weight)) = crossfade.next(mode) {
+                output += (mode_output(previous_mode, i) - output) * weight;
+            }
+            if i > 0 {
+                largest_step = largest_step.max((output - previous).abs());
+            }
+            previous = output;
+        }
+
+        largest_step
+    }
+
+    #[test]
+    fn mode_flip_has_no_large_steps() {
+        // A sine this slow never moves more than 0.05 per sample on its own
+        let faded_step = largest_step(240);
+        assert!(faded_step < 0.06, "largest step {faded_step}");
+        // Without a crossfade the flip jumps straight between the two levels
+        assert!(largest_step(0) > 0.1);
+    }
+}
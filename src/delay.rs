@@ -0,0 +1,79 @@
+/// A plain whole-sample delay for every channel of a frame. This is used for look-ahead, where the
+/// signal is delayed so a detector gets to see it ahead of time, and to keep a signal that skips
+/// part of the chain in step with the latency the main output picks up there.
+#[derive(Debug, Default)]
+pub struct FrameDelay {
+    /// One delay line per channel, long enough for the maximum delay.
+    delay_lines: Vec<Vec<f32>>,
+    delay: usize,
+    pos: usize,
+}
+
+impl FrameDelay {
+    /// Allocate the buffers needed for up to `max_delay` samples of delay. This should be called
+    /// from `initialize()`.
+    pub fn new(num_channels: usize, max_delay: usize) -> Self {
+        Self {
+            delay_lines: vec![vec![0.0; max_delay + 1]; num_channels],
+            delay: 0,
+            pos: 0,
+        }
+    }
+
+    /// Change the delay in samples. This resets the delay lines.
+    pub fn set_delay(&mut self, delay: usize) {
+        let max_delay = self.delay_lines.first().map_or(0, |line| line.len() - 1);
+        self.delay = delay.min(max_delay);
+        self.reset();
+    }
+
+    /// The current delay in samples. This is also the latency it adds.
+    pub fn delay(&self) -> usize {
+        self.delay
+    }
+
+    pub fn reset(&mut self) {
+        for delay_line in &mut self.delay_lines {
+            delay_line.fill(0.0);
+        }
+        self.pos = 0;
+    }
+
+    /// Delay a single frame in place. Without a delay this passes the frame through.
+    pub fn process<'a>(&mut self, frame: impl Iterator<Item = &'a mut f32>) {
+        if self.delay == 0 {
+            return;
+        }
+
+        let window = self.delay + 1;
+        for (sample, delay_line) in frame.zip(&mut self.delay_lines) {
+            delay_line[self.pos] = *sample;
+            *sample = delay_line[(self.pos + 1) % window];
+        }
+        self.pos = (self.pos + 1) % window;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_an_impulse_by_the_delay() {
+        let mut delay = FrameDelay::new(2, 16);
+        delay.set_delay(5);
+
+        let outputs: Vec<[f32; 2]> = (0..10)
+            .map(|i| {
+                let mut frame = if i == 0 { [1.0, -1.0] } else { [0.0, 0.0] };
+                delay.process(frame.iter_mut());
+                frame
+            })
+            .collect();
+
+        for (i, frame) in outputs.iter().enumerate() {
+            let expected = if i == 5 { [1.0, -1.0] } else { [0.0, 0.0] };
+            assert_eq!(*frame, expected, "frame {i}");
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use nih_plug::prelude::util;
+
+use crate::noise::NoiseGenerator;
+use crate::path_eq::{EQ_MAX_HZ, EQ_MIN_HZ};
+
+/// How often per second the random walks take a step.
+const DRIFT_UPDATES_PER_SECOND: f32 = 500.0;
+/// How far each step can move the walks.
+const DRIFT_STEP: f32 = 0.025;
+/// How strongly the walks are pulled back towards the center on every step. This keeps them
+/// wandering around over a few seconds without running off.
+const DRIFT_LEAK: f32 = 0.0005;
+/// How far the wet EQ's cutoffs can drift at full depth.
+const MAX_CUTOFF_DRIFT_OCTAVES: f32 = 0.25;
+/// How far the wet signal's gain can drift at full depth.
+const MAX_GAIN_DRIFT_DB: f32 = 0.5;
+/// The fixed seeds for the random walks, so renders are reproducible.
+const CUTOFF_DRIFT_SEED: u32 = 0x2545_f491;
+const GAIN_DRIFT_SEED: u32 = 0x9e37_79b9;
+
+/// Slow random changes to the wet EQ's cutoffs and the wet signal's gain, like the component
+/// tolerances and temperature changes in analog gear. Both are bounded random walks that take a
+/// few seconds to wander around. The walks are seeded with fixed values and restart on reset, so
+/// the same input always renders the same way.
+#[derive(Debug, Clone)]
+pub struct AnalogDrift {
+    cutoff_noise: NoiseGenerator,
+    gain_noise: NoiseGenerator,
+    /// The walks' current positions in `[-1, 1]`.
+    cutoff_walk: f32,
+    gain_walk: f32,
+    /// The gain walk's previous position, used to interpolate between the steps.
+    previous_gain_walk: f32,
+
+    update_interval: usize,
+    samples: usize,
+}
+
+impl Default for AnalogDrift {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl AnalogDrift {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            cutoff_noise: NoiseGenerator::new(CUTOFF_DRIFT_SEED),
+            gain_noise: NoiseGenerator::new(GAIN_DRIFT_SEED),
+            cutoff_walk: 0.0,
+            gain_walk: 0.0,
+            previous_gain_walk: 0.0,
+
+            update_interval: ((sample_rate / DRIFT_UPDATES_PER_SECOND) as usize).max(1),
+            samples: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self {
+            update_interval: self.update_interval,
+            ..Self::default()
+        };
+    }
+
+    /// Advance the walks by a sample. Returns the ratio the wet EQ's cutoffs should be multiplied
+    /// with and the gain for the wet signal. At zero depth both are exactly 1.
+    pub fn next(&mut self, depth: f32) -> (f32, f32) {
+        self.samples += 1;
+        if self.samples >= self.update_interval {
+            self.samples = 0;
+            self.previous_gain_walk = self.gain_walk;
+            self.cutoff_walk = step_walk(self.cutoff_walk, &mut self.cutoff_noise);
+            self.gain_walk = step_walk(self.gain_walk, &mut self.gain_noise);
+        }
+
+        // The cutoffs only change once per step so the filter coefficients aren't recomputed on
+        // every sample, but the gain needs to move smoothly
+        let t = self.samples as f32 / self.update_interval as f32;
+        let gain_walk = self.previous_gain_walk + (self.gain_walk - self.previous_gain_walk) * t;
+
+        (
+            2.0f32.powf(self.cutoff_walk * depth * MAX_CUTOFF_DRIFT_OCTAVES),
+            util::db_to_gain(gain_walk * depth * MAX_GAIN_DRIFT_DB),
+        )
+    }
+}
+
+/// Move the wet EQ's cutoffs by the drift ratio. Filters that are switched off stay off, since
+/// there's no tone filter to drift in that case.
+pub fn drift_cutoffs((low_cut_hz, high_cut_hz): (f32, f32), ratio: f32) -> (f32, f32) {
+    let low_cut_hz = if low_cut_hz > EQ_MIN_HZ {
+        (low_cut_hz * ratio).clamp(EQ_MIN_HZ, EQ_MAX_HZ)
+    } else {
+        low_cut_hz
+    };
+    let high_cut_hz = if high_cut_hz < EQ_MAX_HZ {
+        (high_cut_hz * ratio).clamp(EQ_MIN_HZ, EQ_MAX_HZ)
+    } else {
+        high_cut_hz
+    };
+
+    (low_cut_hz, high_cut_hz)
+}
+
+fn step_walk(walk: f32, noise: &mut NoiseGenerator) -> f32 {
+    (walk * (1.0 - DRIFT_LEAK) + noise.next() * DRIFT_STEP).clamp(-1.0, 1.0)
+}
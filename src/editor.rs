@@ -1,39 +1,213 @@
-use atomic_float::AtomicF32;
-use nih_plug::prelude::{util, Editor};
+use nih_plug::prelude::{util, Editor, Param, ParamPtr};
 use nih_plug_vizia::vizia::prelude::*;
 use nih_plug_vizia::widgets::*;
 use nih_plug_vizia::{assets, create_vizia_editor, ViziaState, ViziaTheming};
+use std::cell::RefCell;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use crate::chain::WET_STAGE_COUNT;
+use crate::gain_reduction::{GainReductionHistory, GAIN_REDUCTION_HISTORY_LEN};
+use crate::generator::TestSignal;
+use crate::intensity::IntensityMapping;
+use crate::level_match::LevelMatch;
+use crate::meter::{next_refresh_rate_hz, MeterBallistics, PeakMeters};
+use crate::session::SessionInfo;
+use crate::settings;
+use crate::stereo::StereoWarnings;
+use crate::utility::OutputUtilities;
 use crate::DistortionParams;
 
+/// How far past the threshold the gain staging guide aims the driven signal's peaks.
+const GAIN_STAGING_TARGET_DB: f32 = 6.0;
+/// Peaks further than this past the threshold count as too hot.
+const GAIN_STAGING_HOT_DB: f32 = 24.0;
+/// The guide waits for the driven signal to get louder than this before giving advice.
+const GAIN_STAGING_SILENCE_DB: f32 = -60.0;
+
 #[derive(Lens)]
 struct Data {
     params: Arc<DistortionParams>,
-    peak_meter: Arc<AtomicF32>,
+    peak_meters: Arc<PeakMeters>,
+    stereo_warnings: Arc<StereoWarnings>,
+    level_match: Arc<LevelMatch>,
+    output_utilities: Arc<OutputUtilities>,
+    gain_reduction: Arc<GainReductionHistory>,
+    session_info: Arc<SessionInfo>,
+    /// The last report created with `CopyMeterReport`.
+    meter_report: String,
+    /// Counts the held peak resets. The meters are rebuilt whenever this changes, which also
+    /// clears their own hold markers.
+    peak_resets: u32,
+    /// The morph slider's position, which is stored with the plugin's state.
+    morph_position: f32,
+}
+
+enum AppEvent {
+    /// Measure the input and output levels and trim the output to match them.
+    MatchLevels,
+    /// Apply the trim from a finished level measurement to the output parameter.
+    ApplyLevelMatch,
+    /// Switch the digital peak meter to the next release time.
+    CycleMeterDecay,
+    /// Switch to the next lower meter refresh rate.
+    CycleMeterRefreshRate,
+    /// Switch between the summed meter and separate left and right meters.
+    ToggleChannelMeters,
+    /// Show or dismiss the gain staging guide.
+    ToggleGainStagingGuide,
+    /// Clear the held peak so it starts tracking from the current level again.
+    ResetHeldPeak,
+    /// Toggle swapping the left and right output channels.
+    ToggleSwapChannels,
+    /// Toggle summing the output to mono.
+    ToggleMonoSum,
+    /// Clear all of the DSP state on the audio thread.
+    ResetDsp,
+    /// Copy the current parameter values to the clipboard as text.
+    CopySettings,
+    /// Apply parameter values copied with `CopySettings`. Malformed clipboard contents are
+    /// ignored.
+    PasteSettings,
+    /// Copy a snapshot of the current meter readings to the clipboard as text.
+    CopyMeterReport,
+    /// Store the current parameter values in morph slot A, or B if this is true.
+    StoreMorphSlot(bool),
+    /// Move the morph slider to this position and blend the parameters between the two slots.
+    SetMorphPosition(f32),
+    /// Move the wet chain's stage at this position one step earlier.
+    MoveWetStageEarlier(usize),
+}
+
+impl Model for Data {
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
+        event.map(|app_event, _| match app_event {
+            AppEvent::MatchLevels => self.level_match.request(),
+            AppEvent::ApplyLevelMatch => {
+                // The trim replaces the output gain, limited to the parameter's range
+                if let Some(trim_db) = self.level_match.take_result() {
+                    let output = &self.params.output;
+                    let normalized = output.preview_normalized(util::db_to_gain(trim_db));
+                    set_normalized_values(cx, [(output.as_ptr(), normalized)]);
+                    self.level_match
+                        .set_applied(util::gain_to_db(output.preview_plain(normalized)));
+                }
+            }
+            AppEvent::CycleMeterDecay => {
+                let meter_decay = &self.params.meter_decay;
+                meter_decay.set_decay_ms(meter_decay.next_step_ms());
+            }
+            AppEvent::CycleMeterRefreshRate => {
+                let meter_refresh_hz = &self.params.meter_refresh_hz;
+                let next_hz = next_refresh_rate_hz(meter_refresh_hz.load(Ordering::Relaxed));
+                meter_refresh_hz.store(next_hz, Ordering::Relaxed);
+            }
+            AppEvent::ToggleChannelMeters => {
+                let meter_per_channel = &self.params.meter_per_channel;
+                meter_per_channel.fetch_xor(true, Ordering::Relaxed);
+            }
+            AppEvent::ToggleGainStagingGuide => {
+                let gain_staging_guide = &self.params.gain_staging_guide;
+                gain_staging_guide.fetch_xor(true, Ordering::Relaxed);
+            }
+            AppEvent::ResetHeldPeak => {
+                self.peak_meters.reset_held();
+                self.peak_resets = self.peak_resets.wrapping_add(1);
+            }
+            AppEvent::ToggleSwapChannels => self.output_utilities.toggle_swap_channels(),
+            AppEvent::ToggleMonoSum => self.output_utilities.toggle_mono_sum(),
+            AppEvent::ResetDsp => self.output_utilities.request_reset(),
+            AppEvent::CopySettings => {
+                // There's nothing useful to do when the clipboard isn't available
+                let _ = cx.set_clipboard(settings::export_settings(self.params.as_ref()));
+            }
+            AppEvent::PasteSettings => {
+                let values = cx
+                    .get_clipboard()
+                    .ok()
+                    .and_then(|text| settings::parse_settings(self.params.as_ref(), &text));
+                set_normalized_values(cx, values.into_iter().flatten());
+            }
+            AppEvent::CopyMeterReport => {
+                self.meter_report = self.create_meter_report();
+                let _ = cx.set_clipboard(self.meter_report.clone());
+            }
+            AppEvent::StoreMorphSlot(slot_b) => {
+                self.params
+                    .morph_slots
+                    .store(self.params.as_ref(), *slot_b);
+            }
+            AppEvent::SetMorphPosition(position) => {
+                self.morph_position = *position;
+                self.params
+                    .morph_position
+                    .store(*position, Ordering::Relaxed);
+                // The parameters can only be set from the GUI, so the morphing happens here
+                if self.params.morph_slots.is_ready() {
+                    let values = self.params.morph_slots.morph(self.params.as_ref(), *position);
+                    set_normalized_values(cx, values);
+                }
+            }
+            AppEvent::MoveWetStageEarlier(idx) => self.params.wet_chain.move_earlier(*idx),
+        });
+    }
 }
 
-impl Model for Data {}
+impl Data {
+    /// A short text summary of the current meter readings, for logging a track's settings.
+    fn create_meter_report(&self) -> String {
+        let [left, right] = &self.peak_meters.channels;
+        let stereo = match self.stereo_warnings.message() {
+            "" => "OK",
+            message => message,
+        };
+
+        format!(
+            "Distortion: {:.0}%\nOutput: {}\nLeft/right: {} / {}\nHeld peak: {}\n\
+             DC offset: {}\nGain reduction: {:.1} dB\nStereo: {stereo}\nSession: {}",
+            self.peak_meters.distortion.load(Ordering::Relaxed) * 100.0,
+            format_db(self.peak_meters.summed.load(Ordering::Relaxed)),
+            format_db(left.load(Ordering::Relaxed)),
+            format_db(right.load(Ordering::Relaxed)),
+            format_db(self.peak_meters.held.load(Ordering::Relaxed)),
+            format_dc_offsets(&self.peak_meters),
+            self.gain_reduction.get(0),
+            self.session_info.description(),
+        )
+    }
+}
 
 // Makes sense to also define this here, makes it a bit easier to keep track of
 pub(crate) fn default_state() -> Arc<ViziaState> {
-    ViziaState::new(|| (200, 150))
+    ViziaState::new(|| (960, 900))
 }
 
 pub(crate) fn create(
     params: Arc<DistortionParams>,
-    peak_meter: Arc<AtomicF32>,
-    editor_state: Arc<ViziaState>,
+    peak_meters: Arc<PeakMeters>,
+    stereo_warnings: Arc<StereoWarnings>,
+    level_match: Arc<LevelMatch>,
+    output_utilities: Arc<OutputUtilities>,
+    gain_reduction: Arc<GainReductionHistory>,
+    session_info: Arc<SessionInfo>,
 ) -> Option<Box<dyn Editor>> {
+    let editor_state = params.editor_state.clone();
     create_vizia_editor(editor_state, ViziaTheming::Custom, move |cx, _| {
         assets::register_noto_sans_light(cx);
         assets::register_noto_sans_thin(cx);
 
         Data {
             params: params.clone(),
-            peak_meter: peak_meter.clone(),
+            peak_meters: peak_meters.clone(),
+            stereo_warnings: stereo_warnings.clone(),
+            level_match: level_match.clone(),
+            output_utilities: output_utilities.clone(),
+            gain_reduction: gain_reduction.clone(),
+            session_info: session_info.clone(),
+            meter_report: String::new(),
+            peak_resets: 0,
+            morph_position: params.morph_position.load(Ordering::Relaxed),
         }
         .build(cx);
 
@@ -49,20 +223,674 @@ pub(crate) fn create(
                 .child_top(Stretch(1.0))
                 .child_bottom(Pixels(0.0));
 
-            Label::new(cx, "Threshold");
-            ParamSlider::new(cx, Data::params, |params| &params.threshold);
-
-            PeakMeter::new(
-                cx,
-                Data::peak_meter
-                    .map(|peak_meter| util::gain_to_db(peak_meter.load(Ordering::Relaxed))),
-                Some(Duration::from_millis(600)),
-            )
-            // This is how adding padding works in vizia
-            .top(Pixels(10.0));
+            HStack::new(cx, |cx| {
+                VStack::new(cx, |cx| {
+                    Label::new(cx, "Intensity");
+                    ParamSlider::new(cx, Data::params, |params| &params.intensity);
+
+                    Label::new(cx, "Threshold");
+                    // The threshold is pinned to 0 dBFS in brickwall mode, and follows the signal
+                    // with the automatic threshold
+                    ParamSlider::new(cx, Data::params, |params| &params.threshold).disabled(
+                        Data::params.map(|params| {
+                            params.brickwall.value() || params.auto_threshold.value()
+                        }),
+                    );
+                    // The slider shows decibels, this shows the same value as a linear gain for
+                    // gain staging
+                    Label::new(
+                        cx,
+                        Data::params
+                            .map(|params| format!("{:.3} linear", params.threshold.value())),
+                    )
+                    .font_size(12.0);
+                    let guide_params = params.clone();
+                    Binding::new(
+                        cx,
+                        Data::params
+                            .map(|params| params.gain_staging_guide.load(Ordering::Relaxed)),
+                        move |cx, gain_staging_guide| {
+                            if gain_staging_guide.get(cx) {
+                                let hint_params = guide_params.clone();
+                                Label::new(
+                                    cx,
+                                    Data::peak_meters.map(throttled(
+                                        guide_params.clone(),
+                                        move |meters| gain_staging_hint(meters, &hint_params),
+                                    )),
+                                )
+                                .font_size(12.0)
+                                .top(Pixels(5.0));
+                                Button::new(
+                                    cx,
+                                    |cx| cx.emit(AppEvent::ToggleGainStagingGuide),
+                                    |cx| Label::new(cx, "Dismiss"),
+                                );
+                            } else {
+                                Button::new(
+                                    cx,
+                                    |cx| cx.emit(AppEvent::ToggleGainStagingGuide),
+                                    |cx| Label::new(cx, "Gain Staging Guide"),
+                                )
+                                .top(Pixels(5.0));
+                            }
+                        },
+                    );
+                    ParamButton::new(cx, Data::params, |params| &params.auto_threshold)
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.auto_threshold_offset)
+                        .disabled(Data::params.map(|params| {
+                            !params.auto_threshold.value() || params.brickwall.value()
+                        }))
+                        .top(Pixels(5.0));
+                    ParamButton::new(cx, Data::params, |params| &params.split_thresholds)
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.negative_threshold)
+                        .disabled(Data::params.map(|params| {
+                            !params.split_thresholds.value() || params.brickwall.value()
+                        }))
+                        .top(Pixels(5.0));
+
+                    Label::new(cx, "Drive");
+                    ParamSlider::new(cx, Data::params, |params| &params.drive);
+                    ParamSlider::new(cx, Data::params, |params| &params.drive_taper)
+                        .set_style(ParamSliderStyle::CurrentStepLabeled { even: true })
+                        .top(Pixels(5.0));
+                    ParamButton::new(cx, Data::params, |params| &params.drive_compensation)
+                        .top(Pixels(5.0));
+
+                    Label::new(cx, "Mix");
+                    ParamSlider::new(cx, Data::params, |params| &params.mix);
+                    ParamSlider::new(cx, Data::params, |params| &params.mix_law)
+                        .set_style(ParamSliderStyle::CurrentStepLabeled { even: true })
+                        .top(Pixels(5.0));
+
+                    Label::new(cx, "Output");
+                    ParamSlider::new(cx, Data::params, |params| &params.output);
+
+                    ParamButton::new(cx, Data::params, |params| &params.instant)
+                        .top(Pixels(5.0));
+
+                    Button::new(
+                        cx,
+                        |cx| cx.emit(AppEvent::MatchLevels),
+                        |cx| Label::new(cx, "Match levels"),
+                    )
+                    .top(Pixels(5.0));
+                    Label::new(cx, Data::level_match.map(|level_match| level_match.status()))
+                        .font_size(12.0);
+
+                    // The audio thread can't change parameters itself, so the measured trim is
+                    // applied from the GUI as soon as the measurement finishes
+                    Binding::new(
+                        cx,
+                        Data::level_match.map(|level_match| level_match.is_complete()),
+                        |cx, complete| {
+                            if complete.get(cx) {
+                                cx.emit(AppEvent::ApplyLevelMatch);
+                            }
+                        },
+                    );
+
+                    Label::new(cx, "Knee");
+                    ParamSlider::new(cx, Data::params, |params| &params.knee);
+                    ParamSlider::new(cx, Data::params, |params| &params.reclip).top(Pixels(5.0));
+
+                    Label::new(cx, "Deadband");
+                    ParamSlider::new(cx, Data::params, |params| &params.deadband);
+
+                    Label::new(cx, "Emphasis");
+                    ParamSlider::new(cx, Data::params, |params| &params.emphasis);
+                    ParamSlider::new(cx, Data::params, |params| &params.emphasis_pivot)
+                        .disabled(Data::params.map(|params| params.emphasis.value() == 0.0))
+                        .top(Pixels(5.0));
+
+                    Label::new(cx, "Thermal");
+                    ParamSlider::new(cx, Data::params, |params| &params.thermal);
+
+                    Label::new(cx, "Grunge Floor");
+                    ParamSlider::new(cx, Data::params, |params| &params.grunge_floor);
+
+                    HStack::new(cx, |cx| {
+                        ParamButton::new(cx, Data::params, |params| &params.slew_limit);
+                        ParamSlider::new(cx, Data::params, |params| &params.slew_rate)
+                            .disabled(Data::params.map(|params| !params.slew_limit.value()))
+                            .width(Pixels(100.0));
+                    })
+                    .col_between(Pixels(5.0))
+                    .height(Auto)
+                    .top(Pixels(10.0));
+
+                    Label::new(cx, "Even Harmonics");
+                    ParamSlider::new(cx, Data::params, |params| &params.even_harmonics);
+
+                    ParamButton::new(cx, Data::params, |params| &params.brickwall)
+                        .top(Pixels(10.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.mode_crossfade)
+                        .top(Pixels(5.0));
+                    ParamButton::new(cx, Data::params, |params| &params.stereo_link)
+                        .top(Pixels(5.0));
+                })
+                .row_between(Pixels(0.0));
+
+                VStack::new(cx, |cx| {
+                    Label::new(cx, "Compressor");
+                    ParamButton::new(cx, Data::params, |params| &params.compressor);
+                    // The settings only matter while the compressor is enabled
+                    ParamSlider::new(cx, Data::params, |params| &params.compressor_threshold)
+                        .disabled(Data::params.map(|params| !params.compressor.value()))
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.compressor_ratio)
+                        .disabled(Data::params.map(|params| !params.compressor.value()))
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.compressor_attack)
+                        .disabled(Data::params.map(|params| !params.compressor.value()))
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.compressor_release)
+                        .disabled(Data::params.map(|params| !params.compressor.value()))
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.compressor_makeup)
+                        .disabled(Data::params.map(|params| !params.compressor.value()))
+                        .top(Pixels(5.0));
+
+                    Label::new(cx, "Wet Compressor").top(Pixels(10.0));
+                    ParamButton::new(cx, Data::params, |params| &params.wet_compressor);
+                    ParamSlider::new(cx, Data::params, |params| &params.wet_compressor_threshold)
+                        .disabled(Data::params.map(|params| !params.wet_compressor.value()))
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.wet_compressor_ratio)
+                        .disabled(Data::params.map(|params| !params.wet_compressor.value()))
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.wet_compressor_attack)
+                        .disabled(Data::params.map(|params| !params.wet_compressor.value()))
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.wet_compressor_release)
+                        .disabled(Data::params.map(|params| !params.wet_compressor.value()))
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.wet_compressor_makeup)
+                        .disabled(Data::params.map(|params| !params.wet_compressor.value()))
+                        .top(Pixels(5.0));
+
+                    Label::new(cx, "Gate").top(Pixels(10.0));
+                    ParamButton::new(cx, Data::params, |params| &params.gate);
+                    ParamSlider::new(cx, Data::params, |params| &params.gate_division)
+                        .disabled(Data::params.map(|params| !params.gate.value()))
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.gate_rate)
+                        .disabled(Data::params.map(|params| !params.gate.value()))
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.gate_shape)
+                        .set_style(ParamSliderStyle::CurrentStepLabeled { even: true })
+                        .disabled(Data::params.map(|params| !params.gate.value()))
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.gate_depth)
+                        .disabled(Data::params.map(|params| !params.gate.value()))
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.gate_smoothing)
+                        .disabled(Data::params.map(|params| !params.gate.value()))
+                        .top(Pixels(5.0));
+                })
+                .row_between(Pixels(0.0));
+
+                VStack::new(cx, |cx| {
+                    Label::new(cx, "Transients");
+                    ParamButton::new(cx, Data::params, |params| &params.transient_clip);
+                    // The detector settings only matter while transient clipping is enabled
+                    ParamSlider::new(cx, Data::params, |params| &params.transient_sensitivity)
+                        .disabled(Data::params.map(|params| !params.transient_clip.value()))
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.transient_release)
+                        .disabled(Data::params.map(|params| !params.transient_clip.value()))
+                        .top(Pixels(5.0));
+                    ParamButton::new(cx, Data::params, |params| &params.transient_lookahead)
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.transient_lookahead_time)
+                        .disabled(Data::params.map(|params| {
+                            !params.transient_lookahead.value() || params.zero_latency.value()
+                        }))
+                        .top(Pixels(5.0));
+
+                    Label::new(cx, "Band").top(Pixels(10.0));
+                    ParamButton::new(cx, Data::params, |params| &params.band_mode);
+                    ParamSlider::new(cx, Data::params, |params| &params.band_center)
+                        .disabled(Data::params.map(|params| !params.band_mode.value()))
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.band_width)
+                        .disabled(Data::params.map(|params| !params.band_mode.value()))
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.band_solo)
+                        .set_style(ParamSliderStyle::CurrentStepLabeled { even: true })
+                        .disabled(Data::params.map(|params| !params.band_mode.value()))
+                        .top(Pixels(5.0));
+
+                    Label::new(cx, "De-Ess").top(Pixels(10.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.sibilance_sensitivity);
+                    ParamSlider::new(cx, Data::params, |params| &params.sibilance_frequency)
+                        .top(Pixels(5.0));
+
+                    Label::new(cx, "Wet EQ").top(Pixels(10.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.wet_low_cut);
+                    ParamSlider::new(cx, Data::params, |params| &params.wet_high_cut)
+                        .top(Pixels(5.0));
+
+                    Label::new(cx, "Dry EQ").top(Pixels(10.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.dry_low_cut);
+                    ParamSlider::new(cx, Data::params, |params| &params.dry_high_cut)
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.dry_low_cut_slope)
+                        .set_style(ParamSliderStyle::CurrentStepLabeled { even: true })
+                        .top(Pixels(5.0));
+
+                    Label::new(cx, "Crossfeed").top(Pixels(10.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.crossfeed);
+
+                    // Clicking a stage moves it one step earlier, the first stage moves to the
+                    // end
+                    Label::new(cx, "Wet Chain").top(Pixels(10.0));
+                    HStack::new(cx, |cx| {
+                        for idx in 0..WET_STAGE_COUNT {
+                            Button::new(
+                                cx,
+                                move |cx| cx.emit(AppEvent::MoveWetStageEarlier(idx)),
+                                move |cx| {
+                                    Label::new(
+                                        cx,
+                                        Data::params.map(move |params| {
+                                            params.wet_chain.order()[idx].name()
+                                        }),
+                                    )
+                                },
+                            );
+                        }
+                    })
+                    .col_between(Pixels(5.0))
+                    .height(Auto);
+
+                    Label::new(
+                        cx,
+                        Data::morph_position
+                            .map(|position| format!("Morph {:.0}%", position * 100.0)),
+                    )
+                    .top(Pixels(10.0));
+                    HStack::new(cx, |cx| {
+                        Button::new(
+                            cx,
+                            |cx| cx.emit(AppEvent::StoreMorphSlot(false)),
+                            |cx| Label::new(cx, "Store A"),
+                        );
+                        Button::new(
+                            cx,
+                            |cx| cx.emit(AppEvent::StoreMorphSlot(true)),
+                            |cx| Label::new(cx, "Store B"),
+                        );
+                    })
+                    .col_between(Pixels(5.0))
+                    .height(Auto);
+                    Slider::new(cx, Data::morph_position)
+                        .on_changing(|cx, position| cx.emit(AppEvent::SetMorphPosition(position)))
+                        .disabled(Data::params.map(|params| !params.morph_slots.is_ready()))
+                        .top(Pixels(5.0));
+                })
+                .row_between(Pixels(0.0));
+
+                VStack::new(cx, |cx| {
+                    // How hard the clipper works, independent of how loud the signal is
+                    Label::new(
+                        cx,
+                        Data::peak_meters.map(throttled(params.clone(), |meters| {
+                            let distortion = meters.distortion.load(Ordering::Relaxed);
+                            format!("Distortion: {:.0}%", distortion * 100.0)
+                        })),
+                    )
+                    .font_size(20.0);
+
+                    // Rebuilding the meters on a reset clears their hold markers along with the
+                    // held peak
+                    let meter_params = params.clone();
+                    Binding::new(cx, Data::peak_resets, move |cx, _| {
+                        peak_meter_bars(cx, meter_params.clone())
+                    });
+                    HStack::new(cx, |cx| {
+                        Button::new(
+                            cx,
+                            |cx| cx.emit(AppEvent::ToggleChannelMeters),
+                            |cx| Label::new(cx, "L/R Meters"),
+                        )
+                        .checked(
+                            Data::params
+                                .map(|params| params.meter_per_channel.load(Ordering::Relaxed)),
+                        );
+                        // The held peak falls slowly on its own, clicking it resets it right away
+                        Button::new(
+                            cx,
+                            |cx| cx.emit(AppEvent::ResetHeldPeak),
+                            |cx| {
+                                Label::new(
+                                    cx,
+                                    Data::peak_meters.map(throttled(params.clone(), |meters| {
+                                        format!(
+                                            "Peak: {}",
+                                            format_db(meters.held.load(Ordering::Relaxed))
+                                        )
+                                    })),
+                                )
+                            },
+                        );
+                    })
+                    .col_between(Pixels(5.0))
+                    .height(Auto)
+                    .top(Pixels(5.0));
+                    Label::new(
+                        cx,
+                        Data::peak_meters.map(throttled(params.clone(), |meters| {
+                            format!("DC L/R: {}", format_dc_offsets(meters))
+                        })),
+                    )
+                    .font_size(12.0)
+                    .top(Pixels(5.0));
+
+                    Label::new(cx, "Gain Reduction").font_size(12.0).top(Pixels(5.0));
+                    gain_reduction_graph(cx);
+
+                    HStack::new(cx, |cx| {
+                        ParamButton::new(cx, Data::params, |params| &params.limiter);
+                        ParamSlider::new(cx, Data::params, |params| &params.limiter_lookahead)
+                            .disabled(Data::params.map(|params| {
+                                !params.limiter.value() || params.zero_latency.value()
+                            }))
+                            .width(Pixels(100.0));
+                    })
+                    .col_between(Pixels(5.0))
+                    .height(Auto)
+                    .top(Pixels(5.0));
+                    ParamButton::new(cx, Data::params, |params| &params.zero_latency)
+                        .top(Pixels(5.0));
+                    ParamButton::new(cx, Data::params, |params| &params.auto_bypass)
+                        .top(Pixels(5.0));
+                    ParamButton::new(cx, Data::params, |params| &params.safety_clip)
+                        .top(Pixels(5.0));
+                    ParamButton::new(cx, Data::params, |params| &params.double_precision)
+                        .top(Pixels(5.0));
+
+                    ParamSlider::new(cx, Data::params, |params| &params.meter_ballistics)
+                        .set_style(ParamSliderStyle::CurrentStepLabeled { even: true })
+                        .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.meter_source)
+                        .set_style(ParamSliderStyle::CurrentStepLabeled { even: true })
+                        .top(Pixels(5.0));
+                    // The release time only applies to the digital ballistics
+                    Button::new(
+                        cx,
+                        |cx| cx.emit(AppEvent::CycleMeterDecay),
+                        |cx| {
+                            Label::new(
+                                cx,
+                                Data::params.map(|params| {
+                                    format!("Release: {:.0} ms", params.meter_decay.decay_ms())
+                                }),
+                            )
+                        },
+                    )
+                    .disabled(Data::params.map(|params| {
+                        params.meter_ballistics.value() != MeterBallistics::Digital
+                    }))
+                    .top(Pixels(5.0));
+                    Button::new(
+                        cx,
+                        |cx| cx.emit(AppEvent::CycleMeterRefreshRate),
+                        |cx| {
+                            Label::new(
+                                cx,
+                                Data::params.map(|params| {
+                                    let refresh_hz = &params.meter_refresh_hz;
+                                    format!("Refresh: {} Hz", refresh_hz.load(Ordering::Relaxed))
+                                }),
+                            )
+                        },
+                    )
+                    .top(Pixels(5.0));
+
+                    // Monitoring utilities, these aren't saved with the plugin's state
+                    HStack::new(cx, |cx| {
+                        Button::new(
+                            cx,
+                            |cx| cx.emit(AppEvent::ToggleSwapChannels),
+                            |cx| Label::new(cx, "Swap L/R"),
+                        )
+                        .checked(Data::output_utilities.map(|utilities| {
+                            utilities.swap_channels.load(Ordering::Relaxed)
+                        }));
+                        Button::new(
+                            cx,
+                            |cx| cx.emit(AppEvent::ToggleMonoSum),
+                            |cx| Label::new(cx, "Mono"),
+                        )
+                        .checked(
+                            Data::output_utilities
+                                .map(|utilities| utilities.mono_sum.load(Ordering::Relaxed)),
+                        );
+                        // For when a filter or envelope gets stuck in a weird state
+                        Button::new(
+                            cx,
+                            |cx| cx.emit(AppEvent::ResetDsp),
+                            |cx| Label::new(cx, "Reset DSP"),
+                        );
+                    })
+                    .col_between(Pixels(5.0))
+                    .height(Auto)
+                    .top(Pixels(5.0));
+                    ParamSlider::new(cx, Data::params, |params| &params.test_signal)
+                        .set_style(ParamSliderStyle::CurrentStepLabeled { even: true })
+                        .top(Pixels(5.0));
+                    HStack::new(cx, |cx| {
+                        ParamSlider::new(cx, Data::params, |params| &params.test_frequency)
+                            .disabled(Data::params.map(|params| {
+                                params.test_signal.value() != TestSignal::Sine
+                            }))
+                            .width(Pixels(100.0));
+                        ParamSlider::new(cx, Data::params, |params| &params.test_level)
+                            .disabled(Data::params.map(|params| {
+                                params.test_signal.value() == TestSignal::Off
+                            }))
+                            .width(Pixels(100.0));
+                    })
+                    .col_between(Pixels(5.0))
+                    .height(Auto)
+                    .top(Pixels(5.0));
+
+                    Label::new(
+                        cx,
+                        Data::stereo_warnings.map(|warnings| warnings.message().to_string()),
+                    )
+                    .font_size(12.0)
+                    .top(Pixels(5.0));
+                    Label::new(cx, Data::session_info.map(|info| info.description()))
+                        .font_size(12.0)
+                        .top(Pixels(5.0));
+
+                    HStack::new(cx, |cx| {
+                        Button::new(
+                            cx,
+                            |cx| cx.emit(AppEvent::CopySettings),
+                            |cx| Label::new(cx, "Copy settings"),
+                        );
+                        Button::new(
+                            cx,
+                            |cx| cx.emit(AppEvent::PasteSettings),
+                            |cx| Label::new(cx, "Paste settings"),
+                        );
+                    })
+                    .col_between(Pixels(5.0))
+                    .height(Auto)
+                    .top(Pixels(10.0));
+
+                    Button::new(
+                        cx,
+                        |cx| cx.emit(AppEvent::CopyMeterReport),
+                        |cx| Label::new(cx, "Copy meter report"),
+                    )
+                    .top(Pixels(5.0));
+                    Label::new(cx, Data::meter_report)
+                        .font_size(12.0)
+                        .top(Pixels(5.0));
+                })
+                .row_between(Pixels(0.0));
+            })
+            .col_between(Pixels(20.0))
+            .height(Auto)
+            .child_left(Stretch(1.0))
+            .child_right(Stretch(1.0));
         })
         .row_between(Pixels(0.0))
         .child_left(Stretch(1.0))
         .child_right(Stretch(1.0));
     })
 }
+
+/// Wrap a meter reading so its value only changes at the meter refresh rate. The meters are only
+/// redrawn when their values change, so this limits how often that happens.
+fn throttled<T: Clone + 'static>(
+    params: Arc<DistortionParams>,
+    read: impl Fn(&PeakMeters) -> T + 'static,
+) -> impl Fn(&Arc<PeakMeters>) -> T + 'static {
+    let last_reading: RefCell<Option<(Instant, T)>> = RefCell::new(None);
+    move |meters| {
+        let refresh_hz = params.meter_refresh_hz.load(Ordering::Relaxed).max(1);
+        let interval = Duration::from_secs(1) / refresh_hz;
+        let now = Instant::now();
+
+        let mut last_reading = last_reading.borrow_mut();
+        match &*last_reading {
+            Some((time, value)) if now.duration_since(*time) < interval => value.clone(),
+            _ => {
+                let value = read(meters);
+                *last_reading = Some((now, value.clone()));
+                value
+            }
+        }
+    }
+}
+
+/// Format a linear gain value in decibels for the meter readouts.
+fn format_db(gain: f32) -> String {
+    if gain > util::MINUS_INFINITY_GAIN {
+        format!("{:.1} dB", util::gain_to_db(gain))
+    } else {
+        String::from("-inf dB")
+    }
+}
+
+/// Advice on how to change the drive so the peaks going into the clipper land a little past the
+/// threshold, based on the driven signal's held peak.
+fn gain_staging_hint(meters: &PeakMeters, params: &DistortionParams) -> String {
+    if params.auto_threshold.value() {
+        return String::from("Auto Threshold is following the input");
+    }
+
+    let driven = meters.driven.load(Ordering::Relaxed);
+    if driven < util::db_to_gain(GAIN_STAGING_SILENCE_DB) {
+        return String::from("Waiting for signal");
+    }
+
+    // This is the same threshold the clipper uses, minus the thermal effect
+    let threshold = if params.brickwall.value() {
+        1.0
+    } else {
+        params.threshold.value() * IntensityMapping::new(params.intensity.value()).threshold_gain
+    };
+    let over_db = util::gain_to_db(driven / threshold);
+    if over_db < 0.0 {
+        format!(
+            "Too cold, peaks stay {:.1} dB below the threshold. Raise the drive by {:.0} dB",
+            -over_db,
+            GAIN_STAGING_TARGET_DB - over_db
+        )
+    } else if over_db > GAIN_STAGING_HOT_DB {
+        format!(
+            "Very hot, peaks are {:.1} dB past the threshold. Lower the drive by {:.0} dB",
+            over_db,
+            over_db - GAIN_STAGING_TARGET_DB
+        )
+    } else {
+        format!("Good, peaks are {over_db:.1} dB past the threshold")
+    }
+}
+
+/// Format the left and right channels' residual DC offsets in decibels relative to full scale.
+fn format_dc_offsets(meters: &PeakMeters) -> String {
+    let [left, right] = &meters.dc_offsets;
+    format!(
+        "{} / {}",
+        format_db(left.load(Ordering::Relaxed).abs()),
+        format_db(right.load(Ordering::Relaxed).abs())
+    )
+}
+
+/// Set a batch of parameters from their normalized values, as one gesture per parameter.
+fn set_normalized_values(
+    cx: &mut impl EmitContext,
+    values: impl IntoIterator<Item = (ParamPtr, f32)>,
+) {
+    for (param_ptr, normalized_value) in values {
+        cx.emit(RawParamEvent::BeginSetParameter(param_ptr));
+        cx.emit(RawParamEvent::SetParameterNormalized(param_ptr, normalized_value));
+        cx.emit(RawParamEvent::EndSetParameter(param_ptr));
+    }
+}
+
+/// The most gain reduction the graph can show.
+const GAIN_REDUCTION_GRAPH_MAX_DB: f32 = 24.0;
+
+/// A scrolling bar graph of the clipper's recent gain reduction. The newest value is on the right,
+/// and the bars hang down from the top like on a regular gain reduction meter.
+fn gain_reduction_graph(cx: &mut Context) {
+    HStack::new(cx, |cx| {
+        for bar_idx in 0..GAIN_REDUCTION_HISTORY_LEN {
+            let age = GAIN_REDUCTION_HISTORY_LEN - 1 - bar_idx;
+            Element::new(cx)
+                .width(Stretch(1.0))
+                .height(Data::gain_reduction.map(move |gain_reduction| {
+                    let reduction_db =
+                        gain_reduction.get(age).clamp(0.0, GAIN_REDUCTION_GRAPH_MAX_DB);
+                    Percentage(reduction_db / GAIN_REDUCTION_GRAPH_MAX_DB * 100.0)
+                }))
+                .background_color(Color::rgb(200, 80, 60));
+        }
+    })
+    .width(Pixels(180.0))
+    .height(Pixels(40.0))
+    .background_color(Color::rgba(0, 0, 0, 30));
+}
+
+/// The output meter, either summed or as separate left and right meters.
+fn peak_meter_bars(cx: &mut Context, params: Arc<DistortionParams>) {
+    Binding::new(
+        cx,
+        Data::params.map(|params| params.meter_per_channel.load(Ordering::Relaxed)),
+        move |cx, meter_per_channel| {
+            if meter_per_channel.get(cx) {
+                for channel_idx in 0..2 {
+                    PeakMeter::new(
+                        cx,
+                        Data::peak_meters.map(throttled(params.clone(), move |meters| {
+                            let meter = &meters.channels[channel_idx];
+                            util::gain_to_db(meter.load(Ordering::Relaxed))
+                        })),
+                        Some(Duration::from_millis(600)),
+                    )
+                    .top(Pixels(if channel_idx == 0 { 10.0 } else { 2.0 }));
+                }
+            } else {
+                PeakMeter::new(
+                    cx,
+                    Data::peak_meters.map(throttled(params.clone(), |meters| {
+                        util::gain_to_db(meters.summed.load(Ordering::Relaxed))
+                    })),
+                    Some(Duration::from_millis(600)),
+                )
+                // This is how adding padding works in vizia
+                .top(Pixels(10.0));
+            }
+        },
+    );
+}
@@ -0,0 +1,50 @@
+/// A simple peak envelope follower with separate attack and release times.
+#[derive(Debug, Default, Clone)]
+pub struct EnvelopeFollower {
+    attack_coeff: f32,
+    release_coeff: f32,
+
+    envelope: f32,
+}
+
+impl EnvelopeFollower {
+    pub fn new(sample_rate: f32, attack_ms: f32, release_ms: f32) -> Self {
+        let mut follower = Self::default();
+        follower.set_times(sample_rate, attack_ms, release_ms);
+
+        follower
+    }
+
+    /// Recompute the attack and release coefficients. This does not reset the current envelope.
+    pub fn set_times(&mut self, sample_rate: f32, attack_ms: f32, release_ms: f32) {
+        self.attack_coeff = time_constant(sample_rate, attack_ms);
+        self.release_coeff = time_constant(sample_rate, release_ms);
+    }
+
+    pub fn reset(&mut self) {
+        self.envelope = 0.0;
+    }
+
+    /// Feed a sample into the follower and return the updated envelope.
+    pub fn process(&mut self, input: f32) -> f32 {
+        let rectified = input.abs();
+        let coeff = if rectified > self.envelope {
+            self.attack_coeff
+        } else {
+            self.release_coeff
+        };
+        self.envelope = rectified + coeff * (self.envelope - rectified);
+
+        self.envelope
+    }
+}
+
+/// The coefficient for a one pole filter that reaches roughly 63% of a step after `time_ms`
+/// milliseconds. A time of zero results in an instant response.
+pub fn time_constant(sample_rate: f32, time_ms: f32) -> f32 {
+    if time_ms <= 0.0 {
+        0.0
+    } else {
+        (-1.0 / (time_ms / 1000.0 * sample_rate)).exp()
+    }
+}
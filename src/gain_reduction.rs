@@ -0,0 +1,78 @@
+use atomic_float::AtomicF32;
+use nih_plug::prelude::util;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of values kept in the gain reduction history.
+pub const GAIN_REDUCTION_HISTORY_LEN: usize = 64;
+/// How much time each value in the history covers. The graph spans a bit over three seconds.
+const HISTORY_INTERVAL_MS: f32 = 50.0;
+
+/// A short history of how much gain reduction the clipper applied, shared with the editor. Every
+/// value is the largest reduction in decibels during its interval.
+#[derive(Debug)]
+pub struct GainReductionHistory {
+    values: [AtomicF32; GAIN_REDUCTION_HISTORY_LEN],
+    /// The index the next value will be written to.
+    write_pos: AtomicUsize,
+}
+
+impl Default for GainReductionHistory {
+    fn default() -> Self {
+        Self {
+            values: std::array::from_fn(|_| AtomicF32::new(0.0)),
+            write_pos: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl GainReductionHistory {
+    /// The gain reduction in decibels `age` intervals ago, where 0 is the most recent value.
+    pub fn get(&self, age: usize) -> f32 {
+        let write_pos = self.write_pos.load(Ordering::Relaxed);
+        let age = age % GAIN_REDUCTION_HISTORY_LEN + 1;
+        let idx = (write_pos + GAIN_REDUCTION_HISTORY_LEN - age) % GAIN_REDUCTION_HISTORY_LEN;
+
+        self.values[idx].load(Ordering::Relaxed)
+    }
+
+    fn push(&self, reduction_db: f32) {
+        let write_pos = self.write_pos.load(Ordering::Relaxed);
+        self.values[write_pos].store(reduction_db, Ordering::Relaxed);
+        self.write_pos.store(
+            (write_pos + 1) % GAIN_REDUCTION_HISTORY_LEN,
+            Ordering::Relaxed,
+        );
+    }
+}
+
+/// Collects the clipper's gain reduction on the audio thread and adds it to the
+/// [`GainReductionHistory`] once per interval.
+#[derive(Debug, Default)]
+pub struct GainReductionRecorder {
+    interval: usize,
+    samples: usize,
+    /// The lowest gain since the last value was pushed.
+    min_gain: f32,
+}
+
+impl GainReductionRecorder {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            interval: ((HISTORY_INTERVAL_MS / 1000.0 * sample_rate) as usize).max(1),
+            samples: 0,
+            min_gain: 1.0,
+        }
+    }
+
+    /// Add a frame's gain, as the ratio between the clipper's output and input.
+    pub fn add_frame(&mut self, gain: f32, history: &GainReductionHistory) {
+        self.min_gain = self.min_gain.min(gain);
+        self.samples += 1;
+
+        if self.samples >= self.interval {
+            history.push(-util::gain_to_db(self.min_gain));
+            self.samples = 0;
+            self.min_gain = 1.0;
+        }
+    }
+}
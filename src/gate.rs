@@ -0,0 +1,124 @@
+use nih_plug::prelude::{Enum, Transport};
+use std::f32::consts::TAU;
+
+use crate::envelope::time_constant;
+
+/// The length of a single gate step, in note divisions.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateDivision {
+    #[name = "1/1"]
+    Whole,
+    #[name = "1/2"]
+    Half,
+    #[name = "1/4"]
+    Quarter,
+    #[name = "1/8"]
+    Eighth,
+    #[name = "1/16"]
+    Sixteenth,
+    #[name = "1/32"]
+    ThirtySecond,
+}
+
+impl GateDivision {
+    /// The step's length in quarter note beats.
+    pub fn beats(self) -> f64 {
+        match self {
+            GateDivision::Whole => 4.0,
+            GateDivision::Half => 2.0,
+            GateDivision::Quarter => 1.0,
+            GateDivision::Eighth => 0.5,
+            GateDivision::Sixteenth => 0.25,
+            GateDivision::ThirtySecond => 0.125,
+        }
+    }
+}
+
+/// How the gate opens and closes within a step.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateShape {
+    /// A smooth swell that's fully open halfway through the step.
+    Soft,
+    /// Open for the first half of the step and closed for the second half.
+    Hard,
+}
+
+/// A rhythmic gate for the wet signal. While the host's transport is playing the steps follow
+/// the host's tempo and position, and otherwise the gate runs freely at a fixed rate.
+#[derive(Debug, Clone)]
+pub struct TempoGate {
+    /// The position within the current step, in `[0, 1)`.
+    phase: f64,
+    /// How far the phase moves per sample while synced to the host, or `None` when free-running.
+    synced_step: Option<f64>,
+    /// The smoothed gain, so the hard shape doesn't click.
+    gain: f32,
+    smoothing_ms: f32,
+    smoothing_coeff: f32,
+}
+
+impl Default for TempoGate {
+    fn default() -> Self {
+        Self {
+            phase: 0.0,
+            synced_step: None,
+            gain: 1.0,
+            smoothing_ms: 0.0,
+            smoothing_coeff: 0.0,
+        }
+    }
+}
+
+impl TempoGate {
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+        self.gain = 1.0;
+    }
+
+    /// Read the host's transport at the start of a buffer. While the transport is playing and
+    /// reports its tempo and position, the phase is snapped to the host's timeline so the gate
+    /// stays locked to the beat even after seeking.
+    pub fn sync(&mut self, transport: &Transport, division: GateDivision, sample_rate: f32) {
+        let beats = division.beats();
+        self.synced_step = match (transport.playing, transport.tempo, transport.pos_beats()) {
+            (true, Some(tempo), Some(pos_beats)) => {
+                self.phase = (pos_beats / beats).rem_euclid(1.0);
+                Some(tempo / 60.0 / sample_rate as f64 / beats)
+            }
+            _ => None,
+        };
+    }
+
+    /// Update the smoothing time. The coefficient is only recomputed when it changes.
+    pub fn set_smoothing(&mut self, sample_rate: f32, smoothing_ms: f32) {
+        if smoothing_ms != self.smoothing_ms {
+            self.smoothing_ms = smoothing_ms;
+            self.smoothing_coeff = time_constant(sample_rate, smoothing_ms);
+        }
+    }
+
+    /// Advance the gate by a sample and return the gain for the wet signal. `rate_hz` is the
+    /// free-running rate in steps per second, which is only used without a playing transport. At
+    /// zero depth the gain stays at 1.
+    pub fn next(&mut self, rate_hz: f32, depth: f32, shape: GateShape, sample_rate: f32) -> f32 {
+        let open = match shape {
+            GateShape::Soft => 0.5 - 0.5 * (self.phase as f32 * TAU).cos(),
+            GateShape::Hard => {
+                if self.phase < 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        };
+        let target = 1.0 - depth * (1.0 - open);
+        self.gain = target + self.smoothing_coeff * (self.gain - target);
+
+        let step = self
+            .synced_step
+            .unwrap_or(rate_hz as f64 / sample_rate as f64);
+        self.phase = (self.phase + step).fract();
+
+        self.gain
+    }
+}
@@ -0,0 +1,97 @@
+use nih_plug::prelude::Enum;
+use std::f64::consts::TAU;
+
+use crate::noise::NoiseGenerator;
+
+/// The sine sweep's range and how long it takes to go through it once.
+const SWEEP_START_HZ: f64 = 20.0;
+const SWEEP_END_HZ: f64 = 20_000.0;
+const SWEEP_DURATION_SECS: f64 = 10.0;
+/// Brings the pink noise filter's output back to about the same range as the white noise.
+const PINK_NOISE_GAIN: f32 = 0.11;
+
+/// A test signal that replaces the input, for tuning the distortion without a source.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestSignal {
+    Off,
+    Sine,
+    /// A logarithmic sine sweep from 20 Hz to 20 kHz that repeats every 10 seconds.
+    #[name = "Sine Sweep"]
+    Sweep,
+    #[name = "White Noise"]
+    WhiteNoise,
+    #[name = "Pink Noise"]
+    PinkNoise,
+}
+
+/// Generates the [`TestSignal`]s. Every channel gets the same signal.
+#[derive(Debug, Clone)]
+pub struct TestGenerator {
+    phase: f64,
+    /// How far into the current sweep the generator is, in seconds.
+    sweep_time: f64,
+    noise_generator: NoiseGenerator,
+    /// The state of Paul Kellet's pink noise filter.
+    pink_state: [f32; 7],
+}
+
+impl Default for TestGenerator {
+    fn default() -> Self {
+        Self {
+            phase: 0.0,
+            sweep_time: 0.0,
+            noise_generator: NoiseGenerator::new(0x2545_F491),
+            pink_state: [0.0; 7],
+        }
+    }
+}
+
+impl TestGenerator {
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+        self.sweep_time = 0.0;
+        self.pink_state = [0.0; 7];
+    }
+
+    /// Returns the next sample, roughly normalized to `[-1, 1]`. `frequency` is only used for the
+    /// plain sine.
+    pub fn next(&mut self, signal: TestSignal, frequency: f32, sample_rate: f32) -> f32 {
+        match signal {
+            TestSignal::Off => 0.0,
+            TestSignal::Sine => self.next_sine(frequency as f64, sample_rate),
+            TestSignal::Sweep => {
+                let progress = self.sweep_time / SWEEP_DURATION_SECS;
+                let frequency = SWEEP_START_HZ * (SWEEP_END_HZ / SWEEP_START_HZ).powf(progress);
+                self.sweep_time =
+                    (self.sweep_time + 1.0 / sample_rate as f64) % SWEEP_DURATION_SECS;
+
+                self.next_sine(frequency, sample_rate)
+            }
+            TestSignal::WhiteNoise => self.noise_generator.next(),
+            TestSignal::PinkNoise => self.next_pink(),
+        }
+    }
+
+    fn next_sine(&mut self, frequency: f64, sample_rate: f32) -> f32 {
+        let sample = self.phase.sin() as f32;
+        self.phase = (self.phase + TAU * frequency / sample_rate as f64) % TAU;
+
+        sample
+    }
+
+    /// Filtered white noise with a -3 dB per octave slope, using Paul Kellet's refined method.
+    fn next_pink(&mut self) -> f32 {
+        let white = self.noise_generator.next();
+        let [b0, b1, b2, b3, b4, b5, b6] = &mut self.pink_state;
+        *b0 = 0.99886 * *b0 + white * 0.055_517_9;
+        *b1 = 0.99332 * *b1 + white * 0.075_075_9;
+        *b2 = 0.969 * *b2 + white * 0.153_852;
+        *b3 = 0.8665 * *b3 + white * 0.310_485_6;
+        *b4 = 0.55 * *b4 + white * 0.532_952_2;
+        *b5 = -0.7616 * *b5 - white * 0.016_898;
+        let pink = *b0 + *b1 + *b2 + *b3 + *b4 + *b5 + *b6 + white * 0.5362;
+        *b6 = white * 0.115_926;
+
+        pink * PINK_NOISE_GAIN
+    }
+}
@@ -0,0 +1,48 @@
+use crate::envelope::EnvelopeFollower;
+
+/// How hard the saturation drives the waveform, relative to its envelope.
+const GRUNGE_DRIVE: f32 = 2.0;
+const GRUNGE_ATTACK_MS: f32 = 1.0;
+const GRUNGE_RELEASE_MS: f32 = 100.0;
+/// The envelope is kept above this so silence doesn't cause a division by zero.
+const GRUNGE_MIN_ENVELOPE: f32 = 1e-9;
+
+/// A gentle saturation that works the same at every level. The signal is scaled by its own
+/// envelope before it's saturated and scaled back afterwards, so even very quiet signals that never
+/// reach the clipper's threshold get some texture. The peaks stay at about the same level.
+#[derive(Debug, Default, Clone)]
+pub struct GrungeFloor {
+    envelope_follower: EnvelopeFollower,
+}
+
+impl GrungeFloor {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            envelope_follower: EnvelopeFollower::new(
+                sample_rate,
+                GRUNGE_ATTACK_MS,
+                GRUNGE_RELEASE_MS,
+            ),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.envelope_follower.reset();
+    }
+
+    /// Blend in the saturation by `amount`, between 0 and 1. At zero the input is returned as is,
+    /// but the envelope keeps following the signal either way.
+    pub fn process(&mut self, input: f32, amount: f32) -> f32 {
+        let envelope = self
+            .envelope_follower
+            .process(input)
+            .max(GRUNGE_MIN_ENVELOPE);
+        if amount <= 0.0 {
+            return input;
+        }
+
+        let saturated = envelope * (GRUNGE_DRIVE * input / envelope).tanh() / GRUNGE_DRIVE.tanh();
+
+        input + (saturated - input) * amount
+    }
+}
@@ -0,0 +1,37 @@
+use nih_plug::prelude::util;
+
+/// The extra drive at full intensity.
+const MAX_DRIVE_DB: f32 = 24.0;
+/// How far the threshold is pulled down at full intensity.
+const MAX_THRESHOLD_DROP_DB: f32 = 18.0;
+
+/// How the intensity macro modifies the individual parameters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntensityMapping {
+    /// Multiplied with the drive.
+    pub drive_gain: f32,
+    /// Multiplied with the threshold.
+    pub threshold_gain: f32,
+    /// How far the mix is pushed towards fully wet, between 0 and 1.
+    pub wet_amount: f32,
+}
+
+impl IntensityMapping {
+    /// Map an intensity between 0 and 1 to parameter offsets. At zero intensity the individual
+    /// parameters are left untouched. The mix comes in first so the lower half of the knob blends
+    /// in the distortion, after which the drive and threshold take over to destroy the signal.
+    pub fn new(intensity: f32) -> Self {
+        let intensity = intensity.clamp(0.0, 1.0);
+
+        Self {
+            drive_gain: util::db_to_gain(MAX_DRIVE_DB * intensity * intensity),
+            threshold_gain: util::db_to_gain(-MAX_THRESHOLD_DROP_DB * intensity),
+            wet_amount: (intensity * 2.0).min(1.0),
+        }
+    }
+
+    /// Push a mix value towards fully wet.
+    pub fn apply_mix(&self, mix: f32) -> f32 {
+        mix + (1.0 - mix) * self.wet_amount
+    }
+}
@@ -0,0 +1,231 @@
+use atomic_float::AtomicF32;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// How long the input and output levels are measured for when matching levels.
+pub const LEVEL_MATCH_DURATION_SECS: f32 = 1.0;
+
+/// State shared between the editor and the audio thread for the "match levels" action. The editor
+/// requests a measurement, the audio thread measures the input and output RMS levels over
+/// [`LEVEL_MATCH_DURATION_SECS`], and the editor then applies the resulting trim through the
+/// output parameter and reports back what it applied.
+#[derive(Debug)]
+pub struct LevelMatch {
+    requested: AtomicBool,
+    measuring: AtomicBool,
+    progress: AtomicF32,
+
+    /// Set when a new result is waiting to be applied by the editor.
+    complete: AtomicBool,
+    /// The trim needed to match the output to the input, in decibels. This is NaN until the first
+    /// measurement has finished.
+    result_db: AtomicF32,
+    /// The trim the editor actually applied for the last result, which can be less than the
+    /// result because of the output parameter's range. This is NaN when there's nothing to show.
+    applied_db: AtomicF32,
+}
+
+impl Default for LevelMatch {
+    fn default() -> Self {
+        Self {
+            requested: AtomicBool::new(false),
+            measuring: AtomicBool::new(false),
+            progress: AtomicF32::new(0.0),
+
+            complete: AtomicBool::new(false),
+            result_db: AtomicF32::new(f32::NAN),
+            applied_db: AtomicF32::new(f32::NAN),
+        }
+    }
+}
+
+impl LevelMatch {
+    /// Ask the audio thread to start a new measurement.
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete.load(Ordering::Relaxed)
+    }
+
+    /// Returns the trim in decibels if a measurement finished since the last call.
+    pub fn take_result(&self) -> Option<f32> {
+        if self.complete.swap(false, Ordering::Relaxed) {
+            Some(self.result_db.load(Ordering::Relaxed))
+        } else {
+            None
+        }
+    }
+
+    /// Record the trim that was applied for the last result, for the status.
+    pub fn set_applied(&self, applied_db: f32) {
+        self.applied_db.store(applied_db, Ordering::Relaxed);
+    }
+
+    /// A short description of the current state for the editor.
+    pub fn status(&self) -> String {
+        let applied_db = self.applied_db.load(Ordering::Relaxed);
+        if self.measuring.load(Ordering::Relaxed) {
+            format!(
+                "Measuring... {:.0}%",
+                self.progress.load(Ordering::Relaxed) * 100.0
+            )
+        } else if applied_db.is_finite() {
+            format!("Output trimmed by {applied_db:+.1} dB")
+        } else {
+            String::new()
+        }
+    }
+}
+
+/// The audio thread's side of the level matching.
+#[derive(Debug, Default)]
+pub struct LevelMeasurement {
+    length: usize,
+    remaining: usize,
+
+    input_power: f64,
+    output_power: f64,
+}
+
+impl LevelMeasurement {
+    /// Check whether the editor requested a new measurement, and start one if it did.
+    pub fn poll_request(&mut self, shared: &LevelMatch, sample_rate: f32) {
+        if shared.requested.swap(false, Ordering::Relaxed) {
+            self.length = (LEVEL_MATCH_DURATION_SECS * sample_rate).round().max(1.0) as usize;
+            self.remaining = self.length;
+            self.input_power = 0.0;
+            self.output_power = 0.0;
+
+            shared.progress.store(0.0, Ordering::Relaxed);
+            shared.measuring.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.remaining > 0
+    }
+
+    /// Add a frame's summed squared input and output samples to the measurement. When the
+    /// measurement finishes the result is published to the editor.
+    pub fn add_frame(&mut self, input_power: f32, output_power: f32, shared: &LevelMatch) {
+        if self.remaining == 0 {
+            return;
+        }
+
+        self.input_power += input_power as f64;
+        self.output_power += output_power as f64;
+        self.advance(1, shared);
+    }
+
+    /// Count `num_samples` silent frames towards the measurement. The auto bypass skips the
+    /// processing for silent buffers, and this keeps the measurement going while it does.
+    pub fn add_silence(&mut self, num_samples: usize, shared: &LevelMatch) {
+        if self.remaining == 0 {
+            return;
+        }
+
+        self.advance(num_samples, shared);
+    }
+
+    fn advance(&mut self, num_samples: usize, shared: &LevelMatch) {
+        self.remaining = self.remaining.saturating_sub(num_samples);
+
+        if self.remaining == 0 {
+            shared.measuring.store(false, Ordering::Relaxed);
+
+            // Silence on either side can't be matched, so no result is published and the trim is
+            // left alone. The status is cleared so it doesn't look like an old trim was applied.
+            if self.input_power > 1e-9 && self.output_power > 1e-9 {
+                let trim_db = (10.0 * (self.input_power / self.output_power).log10()) as f32;
+                shared.result_db.store(trim_db, Ordering::Relaxed);
+                shared.complete.store(true, Ordering::Relaxed);
+            } else {
+                shared.applied_db.store(f32::NAN, Ordering::Relaxed);
+            }
+        } else {
+            let progress = 1.0 - (self.remaining as f32 / self.length as f32);
+            shared.progress.store(progress, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measure(input_power: f32, output_power: f32) -> LevelMatch {
+        let shared = LevelMatch::default();
+        let mut measurement = LevelMeasurement::default();
+        shared.request();
+        measurement.poll_request(&shared, 100.0);
+        while measurement.is_active() {
+            measurement.add_frame(input_power, output_power, &shared);
+        }
+
+        shared
+    }
+
+    #[test]
+    fn matches_the_output_to_the_input() {
+        let shared = measure(1.0, 0.01);
+
+        let trim_db = shared.take_result().unwrap();
+        assert!((trim_db - 20.0).abs() < 1e-3, "trim {trim_db}");
+    }
+
+    #[test]
+    fn silence_publishes_no_result() {
+        for (input_power, output_power) in [(0.0, 1.0), (1.0, 0.0), (0.0, 0.0)] {
+            let shared = measure(input_power, output_power);
+
+            assert!(!shared.is_complete());
+            assert_eq!(shared.take_result(), None);
+            assert!(!shared.measuring.load(Ordering::Relaxed));
+        }
+    }
+
+    #[test]
+    fn status_shows_the_applied_trim() {
+        let shared = measure(1.0, 1e-4);
+        let trim_db = shared.take_result().unwrap();
+        assert!((trim_db - 40.0).abs() < 1e-3, "trim {trim_db}");
+
+        // The output parameter only goes up to 24 dB
+        shared.set_applied(24.0);
+        assert_eq!(shared.status(), "Output trimmed by +24.0 dB");
+    }
+
+    #[test]
+    fn discarded_measurement_clears_the_status() {
+        let shared = measure(1.0, 0.01);
+        shared.set_applied(shared.take_result().unwrap());
+        assert_eq!(shared.status(), "Output trimmed by +20.0 dB");
+
+        let mut measurement = LevelMeasurement::default();
+        shared.request();
+        measurement.poll_request(&shared, 100.0);
+        while measurement.is_active() {
+            measurement.add_frame(0.0, 0.0, &shared);
+        }
+        assert_eq!(shared.status(), "");
+    }
+
+    #[test]
+    fn silence_keeps_the_measurement_going() {
+        let shared = LevelMatch::default();
+        let mut measurement = LevelMeasurement::default();
+        shared.request();
+        measurement.poll_request(&shared, 100.0);
+        for _ in 0..50 {
+            measurement.add_frame(1.0, 0.01, &shared);
+        }
+        assert!(shared.status().starts_with("Measuring... 50%"));
+
+        // The rest of the measurement is skipped by the auto bypass
+        measurement.add_silence(64, &shared);
+        assert!(!measurement.is_active());
+        let trim_db = shared.take_result().unwrap();
+        assert!((trim_db - 20.0).abs() < 1e-3, "trim {trim_db}");
+    }
+}
@@ -1,10 +1,114 @@
 use atomic_float::AtomicF32;
 use nih_plug_vizia::ViziaState;
 use nih_plug::prelude::*;
+use std::sync::atomic::{AtomicBool, AtomicU32};
 use std::sync::Arc;
 
+use asymmetry::{asymmetric_clip, DcBlocker};
+use band::BandSolo;
+use biquad::{Biquad, BiquadCoefficients};
+use ceiling::CeilingFollower;
+use chain::{WetChain, WetStage};
+use compensation::DriveCompensation;
+use compressor::Compressor;
+use crossfade::ModeCrossfade;
+use delay::FrameDelay;
+use drift::{drift_cutoffs, AnalogDrift};
+use envelope::EnvelopeFollower;
+use gain_reduction::{GainReductionHistory, GainReductionRecorder};
+use gate::{GateDivision, GateShape, TempoGate};
+use generator::{TestGenerator, TestSignal};
+use grunge::GrungeFloor;
+use intensity::IntensityMapping;
+use level_match::{LevelMatch, LevelMeasurement};
+use limiter::{LookaheadLimiter, MAX_LOOKAHEAD_MS};
+use meter::{
+    dc_offset_weight, distortion_weight, peak_hold_fall_weight, MeterBallistics,
+    MeterCoefficients, MeterDecay, MeterSource, PeakMeters, METER_REFRESH_RATES_HZ,
+};
+use mix::MixLaw;
+use morph::MorphSlots;
+use noise::{HumFrequency, HumGenerator, NoiseGenerator};
+use path_eq::{LowCutSlope, PathEq, EQ_MAX_HZ, EQ_MIN_HZ};
+use poly_mod::{PolyDriveModulation, DRIVE_POLY_MOD_ID, MAX_VOICES};
+use session::SessionInfo;
+use sibilance::SibilanceClipper;
+use slew::SlewLimiter;
+use stereo::{StereoAnalyzer, StereoWarnings};
+use taper::{DriveTaper, DRIVE_MAX_DB};
+use tape::{DelayInterpolation, WowFlutter};
+use task::{MeterLogger, Task};
+use thermal::ThermalState;
+use transient::{TransientDetector, MAX_TRANSIENT_LOOKAHEAD_MS};
+use utility::{OutputUtilities, ResetFade, UtilityProcessor};
+
+mod asymmetry;
+mod band;
+mod biquad;
+mod ceiling;
+mod chain;
+mod compensation;
+mod compressor;
+mod crossfade;
+mod delay;
+mod drift;
 mod editor;
-const PEAK_METER_DECAY_MS: f64 = 150.0;
+mod envelope;
+mod gain_reduction;
+mod gate;
+mod generator;
+mod grunge;
+mod intensity;
+mod level_match;
+mod limiter;
+mod meter;
+mod mix;
+mod morph;
+mod noise;
+mod path_eq;
+mod poly_mod;
+mod session;
+mod settings;
+mod sibilance;
+mod slew;
+mod stereo;
+mod tape;
+mod task;
+mod taper;
+mod thermal;
+mod transient;
+mod utility;
+
+/// The level of the analog noise when the noise parameter is turned all the way up, about -36 dBFS.
+const NOISE_MAX_GAIN: f32 = 0.015;
+/// The noise fades in as the input envelope approaches this level (-40 dBFS), so silence stays
+/// silent instead of hissing.
+const NOISE_GATE_LEVEL: f32 = 0.01;
+const NOISE_GATE_ATTACK_MS: f32 = 5.0;
+const NOISE_GATE_RELEASE_MS: f32 = 200.0;
+
+/// The default for how long the output crossfades between the old and new transfer function
+/// after a mode change.
+const MODE_CROSSFADE_MS: f32 = 5.0;
+/// The longest mode crossfade the parameter allows.
+const MAX_MODE_CROSSFADE_MS: f32 = 200.0;
+
+/// Input below this level (-90 dBFS) counts as silence for the auto bypass.
+const AUTO_BYPASS_THRESHOLD: f32 = 3.162e-5;
+/// How long the input needs to stay silent before the processing is skipped. This is long enough
+/// for the noise gate and the limiter to settle, so nothing gets cut off.
+const AUTO_BYPASS_HOLD_MS: f32 = 500.0;
+
+/// The safety clip's ceiling, just below 0 dBFS for a little headroom.
+const SAFETY_CLIP_CEILING: f32 = 0.989; // -0.1 dBFS
+/// The part of the safety clip's ceiling that's spent on its knee. Everything below about -2.5
+/// dBFS passes through untouched.
+const SAFETY_CLIP_KNEE: f32 = 0.25;
+/// The most times the clipper can be run in series. Every extra pass costs another clipper per
+/// sample, so this is kept low.
+const MAX_RECLIP_PASSES: i32 = 8;
+/// The gain applied between re-clip passes, driving every pass 6 dB harder into the clipper.
+const RECLIP_GAIN: f32 = 2.0;
 
 
 // This is a shortened version of the gain example with most comments removed, check out
@@ -14,9 +118,162 @@ const PEAK_METER_DECAY_MS: f64 = 150.0;
 pub struct Distortion {
     params: Arc<DistortionParams>,
 
-    peak_meter_decay_weight: f32,
+    /// The smoothing weights for the peak meter, recomputed when the ballistics or the digital
+    /// meter's release weight change.
+    meter_coefficients: MeterCoefficients,
+    meter_ballistics: MeterBallistics,
+    meter_release_weight: f32,
+
+    peak_meters: Arc<PeakMeters>,
+    /// The host's sample rate and block size, shown in the editor.
+    session_info: Arc<SessionInfo>,
+    /// The per-sample weight for the held peak's fall, which only depends on the sample rate.
+    peak_hold_weight: f32,
+    /// The per-sample weight for the residual DC readout's average, see [`dc_offset_weight()`].
+    dc_offset_weight: f32,
+    /// The per-sample weight for the distortion amount's average, see [`distortion_weight()`].
+    distortion_weight: f32,
+
+    sample_rate: f32,
+
+    /// Smooths the switch between the regular and brickwall thresholds.
+    brickwall_crossfade: ModeCrossfade<bool>,
+
+    /// CLAP polyphonic modulation offsets for the drive parameter.
+    poly_drive: PolyDriveModulation,
+
+    /// Per-channel input envelopes, used to gate the analog noise.
+    envelopes: Vec<EnvelopeFollower>,
+    /// Per-channel white noise generators, seeded in `initialize()`.
+    noise_generators: Vec<NoiseGenerator>,
+    hum_generator: HumGenerator,
+    /// The built-in test signal that can replace the input.
+    test_generator: TestGenerator,
+
+    /// Compares the input and output stereo images to warn about collapsed or inverted stereo.
+    stereo_analyzer: StereoAnalyzer,
+    stereo_warnings: Arc<StereoWarnings>,
+
+    /// The optional look-ahead limiter on the output.
+    limiter: LookaheadLimiter,
+    /// Whether the limiter was enabled during the last process call. Used to detect latency
+    /// changes.
+    limiter_enabled: bool,
+
+    /// The "Distortion Only" aux output's current frame. This goes through the same wow and
+    /// flutter, latency and final safety stages as the main output so the two stay aligned.
+    aux_frame: Vec<f32>,
+    aux_wow_flutter: WowFlutter,
+    /// Delays the aux output by the limiter's look-ahead, without limiting it.
+    aux_delay: FrameDelay,
+
+    /// Measures the input and output levels when the editor asks for the output to be matched.
+    level_match: Arc<LevelMatch>,
+    level_measurement: LevelMeasurement,
+
+    /// Per-channel transient detectors for the transient-only clipping mode.
+    transient_detectors: Vec<TransientDetector>,
+    /// The transient release time the detectors were last configured with.
+    transient_release_ms: f32,
+    /// Delays the clipper's input so the transient detectors can look ahead.
+    transient_lookahead: FrameDelay,
+    /// The undelayed input for the transient detectors, one sample per channel.
+    transient_lookahead_frame: Vec<f32>,
+
+    /// Per-channel band-pass filters that pick out the band that gets distorted in band mode.
+    band_filters: Vec<Biquad>,
+    /// The center frequency and width the band filters were last configured with.
+    band_settings: (f32, f32),
 
-    peak_meter: Arc<AtomicF32>,
+    /// Per-channel emphasis filters before the clipper, with the matching de-emphasis filters
+    /// after it.
+    emphasis_filters: Vec<(Biquad, Biquad)>,
+    /// The emphasis amount and pivot the filters were last configured with.
+    emphasis_settings: (f32, f32),
+
+    /// Per-channel DC blockers for the asymmetric clipper used by the even harmonics control.
+    dc_blockers: Vec<DcBlocker>,
+    /// The peak follower for the automatic threshold.
+    ceiling_follower: CeilingFollower,
+    /// Per-channel DC blockers for the offset caused by split thresholds.
+    split_dc_blockers: Vec<DcBlocker>,
+    /// Per-channel slew rate limiters for the slew limit mode.
+    slew_limiters: Vec<SlewLimiter>,
+    /// Keeps the wet level steady while the drive changes.
+    drive_compensation: DriveCompensation,
+    /// The linked compressor in front of the clipper.
+    compressor: Compressor,
+    /// Per-channel compressors for the distorted signal.
+    wet_compressors: Vec<Compressor>,
+    /// The clipper's slowly changing temperature for the thermal control.
+    thermal: ThermalState,
+    /// Per-channel de-esser style clippers for the sibilance band.
+    sibilance_clippers: Vec<SibilanceClipper>,
+    /// Per-channel level independent saturation for the grunge floor.
+    grunge_floors: Vec<GrungeFloor>,
+
+    /// The swap and mono monitoring toggles from the editor.
+    output_utilities: Arc<OutputUtilities>,
+    utility_processor: UtilityProcessor,
+    /// Fades around a DSP reset requested from the editor. Unlike everything else, this isn't
+    /// reset in `reset()`, since it's what calls `reset()`.
+    reset_fade: ResetFade,
+
+    /// How many samples in a row the input has been silent for, used by the auto bypass.
+    silent_samples: usize,
+    /// Collects the readings for the meter log, which is written on the background thread.
+    meter_logger: MeterLogger,
+
+    /// Tape-style pitch wobble on the output.
+    wow_flutter: WowFlutter,
+    /// Slow random movement of the wet EQ and gain.
+    analog_drift: AnalogDrift,
+    /// The rhythmic gate on the wet signal.
+    tempo_gate: TempoGate,
+
+    /// The clipper's recent gain reduction, graphed in the editor.
+    gain_reduction: Arc<GainReductionHistory>,
+    gain_reduction_recorder: GainReductionRecorder,
+
+    /// Per-channel tone shaping for the wet and dry paths, applied before they're mixed.
+    wet_eq: Vec<PathEq>,
+    dry_eq: Vec<PathEq>,
+    /// The wet EQ for the unclipped signal the transient stage blends back to, so that signal gets
+    /// the same filtering when the EQ comes first.
+    transient_reference_eq: Vec<PathEq>,
+
+    /// The clean input and the filtered dry signal for every channel in the current frame. These
+    /// are kept around while the wet signals are crossfed between the channels.
+    dry_frame: Vec<(f32, f32)>,
+    /// Every channel's signals right after the clipper in the current frame, so the gain
+    /// reduction can be linked between the channels.
+    clip_frame: Vec<ClippedSample>,
+}
+
+/// A channel's signals right after the clipper.
+#[derive(Debug, Default, Clone, Copy)]
+struct ClippedSample {
+    clean: f32,
+    /// The signal going into the drive, which is just the band in band mode.
+    clip_input: f32,
+    /// The part of the signal that bypasses the clipper in band mode.
+    passthrough: f32,
+    driven: f32,
+    /// The gain the symmetric clipper applied on its own, before the split thresholds, the
+    /// brickwall fade and the even harmonics shaped the output further.
+    symmetric_gain: f32,
+    output: f32,
+}
+
+/// What the meters and the analysis need to know about a frame once it's been processed.
+struct ProcessedFrame {
+    /// The input before anything touched it, if the plugin runs in stereo.
+    input_frame: Option<(f32, f32)>,
+    /// The lowest gain the clipper applied to any channel.
+    clipper_gain: f32,
+    /// The driven signal's power and the power of what the clipper changed about it.
+    distortion_frame: (f32, f32),
+    driven_peak: f32,
 }
 
 #[derive(Params)]
@@ -28,9 +285,387 @@ struct DistortionParams {
     #[id = "threshold"]
     pub threshold: FloatParam,
 
+    /// Gives the negative half of the waveform its own threshold. The main threshold then only
+    /// applies to the positive half. The uneven clipping adds even harmonics, and the DC offset
+    /// it causes is filtered out again.
+    #[id = "split-thresholds"]
+    pub split_thresholds: BoolParam,
+
+    /// The negative half's threshold when the thresholds are split.
+    #[id = "negative-threshold"]
+    pub negative_threshold: FloatParam,
+
+    /// Lets the threshold follow the recent peak level of the driven signal instead of using the
+    /// threshold parameter, so the clipper always bites about equally hard.
+    #[id = "auto-threshold"]
+    pub auto_threshold: BoolParam,
+
+    /// How far below the followed peak the automatic threshold sits.
+    #[id = "auto-threshold-offset"]
+    pub auto_threshold_offset: FloatParam,
+
     #[id = "mix"]
     pub mix: FloatParam,
 
+    /// The crossfade curve used by the mix parameter.
+    #[id = "mix-law"]
+    pub mix_law: EnumParam<MixLaw>,
+
+    /// Filters for shaping the distorted and clean signals separately before they're mixed. The
+    /// low cuts are off at their lowest setting and the high cuts at their highest.
+    #[id = "wet-low-cut"]
+    pub wet_low_cut: FloatParam,
+
+    #[id = "wet-high-cut"]
+    pub wet_high_cut: FloatParam,
+
+    #[id = "dry-low-cut"]
+    pub dry_low_cut: FloatParam,
+
+    #[id = "dry-high-cut"]
+    pub dry_high_cut: FloatParam,
+
+    /// How steeply the dry low cut rolls off, which sets how much clean low end is left in the
+    /// blend.
+    #[id = "dry-low-cut-slope"]
+    pub dry_low_cut_slope: EnumParam<LowCutSlope>,
+
+    /// Feeds part of each channel's distorted signal into the other channel before the mix. At
+    /// 100% both channels get an even blend of the two.
+    #[id = "crossfeed"]
+    pub crossfeed: FloatParam,
+
+    /// A macro that raises the drive and mix and lowers the threshold all at once, going from
+    /// the current settings to completely destroyed. The individual parameters still work as
+    /// usual.
+    #[id = "intensity"]
+    pub intensity: FloatParam,
+
+    /// Gain applied to the signal before it hits the clipper. CLAP hosts can modulate this
+    /// polyphonically, see [`PolyDriveModulation`].
+    #[id = "drive"]
+    pub drive: FloatParam,
+
+    /// How the drive knob's travel maps to the drive gain. The drive's readout always shows the
+    /// knob position on a linear decibel scale.
+    #[id = "drive-taper"]
+    pub drive_taper: EnumParam<DriveTaper>,
+
+    /// Compensates the wet signal's level for the drive, so sweeping the drive changes the
+    /// timbre instead of the loudness. See [`DriveCompensation`] for how the level is estimated.
+    #[id = "drive-compensation"]
+    pub drive_compensation: BoolParam,
+
+    /// Pins the threshold to 0 dBFS so the drive pushes the signal into a brickwall ceiling.
+    #[id = "brickwall"]
+    pub brickwall: BoolParam,
+
+    /// How long switching brickwall mode on or off crossfades between the two. At zero the switch
+    /// is instant, which can click.
+    #[id = "mode-crossfade"]
+    pub mode_crossfade: FloatParam,
+
+    /// Applies the gain reduction of the hardest clipped channel to all channels, instead of
+    /// clipping every channel on its own. This keeps the stereo image from shifting on loud
+    /// transients at the cost of some loudness and harmonic detail.
+    #[id = "stereo-link"]
+    pub stereo_link: BoolParam,
+
+    /// Blends symmetric clipping, which only adds odd harmonics, with asymmetric clipping, which
+    /// adds even harmonics for a warmer sound.
+    #[id = "even-harmonics"]
+    pub even_harmonics: FloatParam,
+
+    /// How gently the signal runs into the clipping ceiling, from a flat clamp at 0% to a soft,
+    /// limiter-like curve that starts reducing the gain well below the threshold at 100%.
+    #[id = "knee"]
+    pub knee: FloatParam,
+
+    /// How many times the signal goes through the clipper in a row. Every extra pass boosts the
+    /// previous pass's output back into the clipper, which squares the waveform off further. At 1
+    /// this is the regular clipper.
+    #[id = "reclip"]
+    pub reclip: IntParam,
+
+    /// Removes the center of the waveform instead of the peaks, like the crossover distortion of a
+    /// badly biased class B amplifier. Everything within this level of zero is silenced and the
+    /// rest of the signal is pulled in towards the center.
+    #[id = "deadband"]
+    pub deadband: FloatParam,
+
+    /// Lets the clipper heat up when it's pushed hard for a while, which makes it clip a bit
+    /// earlier and softer until it cools down again over several seconds. At zero the clipper
+    /// doesn't change.
+    #[id = "thermal"]
+    pub thermal: FloatParam,
+
+    /// Adds a gentle saturation that works the same at every level, so even quiet signals below
+    /// the threshold get some grit. At 0% everything below the threshold stays clean.
+    #[id = "grunge-floor"]
+    pub grunge_floor: FloatParam,
+
+    /// Limits how fast the clipped signal can change, like an op-amp that can't keep up. Fast
+    /// transients are turned into ramps and loud high frequencies into triangle waves.
+    #[id = "slew-limit"]
+    pub slew_limit: BoolParam,
+
+    /// The fastest the signal can change in slew limit mode, in full scale per millisecond.
+    #[id = "slew-rate"]
+    pub slew_rate: FloatParam,
+
+    /// Makes continuous parameters respond instantly instead of being smoothed. Useful for
+    /// step-sequenced automation, but abrupt changes will click.
+    #[id = "instant"]
+    pub instant: BoolParam,
+
+    /// A gain trim applied after the dry/wet mix.
+    #[id = "output"]
+    pub output: FloatParam,
+
+    /// Compresses the signal before it reaches the clipper, which evens out how hard it gets
+    /// driven. The dry signal isn't compressed.
+    #[id = "compressor"]
+    pub compressor: BoolParam,
+
+    #[id = "compressor-threshold"]
+    pub compressor_threshold: FloatParam,
+
+    #[id = "compressor-ratio"]
+    pub compressor_ratio: FloatParam,
+
+    #[id = "compressor-attack"]
+    pub compressor_attack: FloatParam,
+
+    #[id = "compressor-release"]
+    pub compressor_release: FloatParam,
+
+    /// Gain applied after the compressor to make up for the reduction, before the drive.
+    #[id = "compressor-makeup"]
+    pub compressor_makeup: FloatParam,
+
+    /// Compresses the distorted signal after the clipper to glue the harmonics together and tame
+    /// spiky output. Every channel is compressed on its own, and the dry signal isn't compressed.
+    #[id = "wet-compressor"]
+    pub wet_compressor: BoolParam,
+
+    #[id = "wet-compressor-threshold"]
+    pub wet_compressor_threshold: FloatParam,
+
+    #[id = "wet-compressor-ratio"]
+    pub wet_compressor_ratio: FloatParam,
+
+    #[id = "wet-compressor-attack"]
+    pub wet_compressor_attack: FloatParam,
+
+    #[id = "wet-compressor-release"]
+    pub wet_compressor_release: FloatParam,
+
+    /// Gain applied after the wet compressor to make up for the reduction.
+    #[id = "wet-compressor-makeup"]
+    pub wet_compressor_makeup: FloatParam,
+
+    /// Only clip the transients, letting sustained material through untouched.
+    #[id = "transient-clip"]
+    pub transient_clip: BoolParam,
+
+    /// How easily the transient detector triggers. At 100% everything gets clipped.
+    #[id = "transient-sensitivity"]
+    pub transient_sensitivity: FloatParam,
+
+    /// How long the clipper stays engaged after a transient.
+    #[id = "transient-release"]
+    pub transient_release: FloatParam,
+
+    /// Lets the transient detector look ahead so the clipper is already engaged when a transient
+    /// arrives, instead of reacting to it. This adds latency.
+    #[id = "transient-lookahead"]
+    pub transient_lookahead: BoolParam,
+
+    /// How far ahead the transient detector looks, and thus the latency it adds.
+    #[id = "transient-lookahead-time"]
+    pub transient_lookahead_time: FloatParam,
+
+    /// Boosts the highs above the pivot before the clipper and cuts them by the same amount
+    /// afterwards, like the pre-emphasis in tape machines. The highs then distort more without
+    /// making the overall tone brighter. At 0 dB both filters are flat.
+    #[id = "emphasis"]
+    pub emphasis: FloatParam,
+
+    /// The frequency the emphasis filters tilt around.
+    #[id = "emphasis-pivot"]
+    pub emphasis_pivot: FloatParam,
+
+    /// Only distort a single frequency band and pass the rest of the spectrum through clean.
+    #[id = "band-mode"]
+    pub band_mode: BoolParam,
+
+    /// The center frequency of the distorted band.
+    #[id = "band-center"]
+    pub band_center: FloatParam,
+
+    /// The width of the distorted band in octaves.
+    #[id = "band-width"]
+    pub band_width: FloatParam,
+
+    /// Plays only the selected band, before or after it's distorted, for tuning the band.
+    #[id = "band-solo"]
+    pub band_solo: EnumParam<BandSolo>,
+
+    /// Clips the high frequencies harder while they dominate the signal, like a de-esser. This
+    /// keeps distorted vocals from getting harsh on sibilance. At 0% the clipping is flat.
+    #[id = "sibilance-sensitivity"]
+    pub sibilance_sensitivity: FloatParam,
+
+    /// Where the sibilance band starts.
+    #[id = "sibilance-frequency"]
+    pub sibilance_frequency: FloatParam,
+
+    /// The amount of analog-style noise mixed into the output while signal is present.
+    #[id = "noise"]
+    pub noise: FloatParam,
+
+    /// Adds mains hum to the analog noise.
+    #[id = "hum"]
+    pub hum: EnumParam<HumFrequency>,
+
+    /// Replaces the input with a test signal for tuning the distortion without a source. This is
+    /// saved with the plugin's state like every other parameter, so remember to switch it off.
+    #[id = "test-signal"]
+    pub test_signal: EnumParam<TestSignal>,
+
+    /// The plain sine's frequency.
+    #[id = "test-frequency"]
+    pub test_frequency: FloatParam,
+
+    #[id = "test-level"]
+    pub test_level: FloatParam,
+
+    /// How much slow, tape-style pitch wobble is added to the output.
+    #[id = "wow-depth"]
+    pub wow_depth: FloatParam,
+
+    #[id = "wow-rate"]
+    pub wow_rate: FloatParam,
+
+    /// How much fast, tape-style pitch flutter is added to the output.
+    #[id = "flutter-depth"]
+    pub flutter_depth: FloatParam,
+
+    #[id = "flutter-rate"]
+    pub flutter_rate: FloatParam,
+
+    /// How the wow and flutter delay line is read between samples. Cubic keeps more of the highs
+    /// while the pitch wobbles, linear is cheaper.
+    #[id = "wow-interpolation"]
+    pub wow_interpolation: EnumParam<DelayInterpolation>,
+
+    /// Slowly wanders the wet EQ's cutoffs and the distortion's level around, like analog gear
+    /// warming up. At zero the processing is completely static.
+    #[id = "drift"]
+    pub drift: FloatParam,
+
+    /// Chops the distorted signal up into rhythmic steps. The steps follow the host's tempo while
+    /// the transport is playing, and the free-running rate otherwise.
+    #[id = "gate"]
+    pub gate: BoolParam,
+
+    /// The length of each gate step when synced to the host.
+    #[id = "gate-division"]
+    pub gate_division: EnumParam<GateDivision>,
+
+    /// The steps per second when there's no playing transport to sync to.
+    #[id = "gate-rate"]
+    pub gate_rate: FloatParam,
+
+    #[id = "gate-shape"]
+    pub gate_shape: EnumParam<GateShape>,
+
+    /// How far the gate closes. At 100% the distortion is completely muted between the steps.
+    #[id = "gate-depth"]
+    pub gate_depth: FloatParam,
+
+    /// How quickly the gate opens and closes. This mostly matters for the hard shape, where a
+    /// bit of smoothing avoids clicks.
+    #[id = "gate-smoothing"]
+    pub gate_smoothing: FloatParam,
+
+    /// How the output meter responds. This only affects the display.
+    #[id = "meter-ballistics"]
+    pub meter_ballistics: EnumParam<MeterBallistics>,
+
+    /// Which signal the main meter shows. This only affects the display.
+    #[id = "meter-source"]
+    pub meter_source: EnumParam<MeterSource>,
+
+    /// The digital meter's release time, set from the editor.
+    #[persist = "meter-decay"]
+    pub meter_decay: MeterDecay,
+
+    /// Whether the editor shows separate left and right meters instead of a single summed one.
+    #[persist = "meter-per-channel"]
+    pub meter_per_channel: AtomicBool,
+
+    /// How often the editor redraws the meters, in Hz. This only changes the display, the levels
+    /// are measured the same way.
+    #[persist = "meter-refresh-rate"]
+    pub meter_refresh_hz: AtomicU32,
+
+    /// Whether the editor shows hints about how hard the signal hits the clipper. This is off by
+    /// default and can be dismissed from the editor.
+    #[persist = "gain-staging-guide"]
+    pub gain_staging_guide: AtomicBool,
+
+    /// The order of the reorderable stages on the wet path, set from the editor.
+    #[persist = "wet-chain"]
+    pub wet_chain: WetChain,
+
+    /// Enables a transparent look-ahead limiter at the very end of the chain. This adds latency.
+    #[id = "limiter"]
+    pub limiter: BoolParam,
+
+    /// How far ahead the limiter looks for peaks, and thus the latency it adds.
+    #[id = "limiter-lookahead"]
+    pub limiter_lookahead: FloatParam,
+
+    /// Disables everything that adds latency, for live monitoring. The limiter still works, but
+    /// without look-ahead it reacts to peaks as they happen.
+    #[id = "zero-latency"]
+    pub zero_latency: BoolParam,
+
+    /// Skips the processing while the input is silent to save CPU. Leave this off if effects
+    /// further down the chain should keep receiving the noise floor.
+    #[id = "auto-bypass"]
+    pub auto_bypass: BoolParam,
+
+    /// A gentle tanh soft clip just below 0 dBFS at the very end of the chain that catches the
+    /// occasional stray peak. This is separate from the main clipper and is on by default.
+    #[id = "safety-clip"]
+    pub safety_clip: BoolParam,
+
+    /// A hard limit far above 0 dBFS for the signal inside the distortion chain. This never
+    /// affects normal material, it only keeps extreme drive settings from producing values so
+    /// large that the filters and soft clipping curves turn them into infinities or NaNs.
+    #[id = "internal-ceiling"]
+    pub internal_ceiling: FloatParam,
+
+    /// Runs the band-pass and DC filters in double precision. The clipping curves always run in
+    /// single precision, so this only affects the filters.
+    #[id = "double-precision"]
+    pub double_precision: BoolParam,
+
+    /// Writes the output peak, gain reduction and distortion amount to the log once per second,
+    /// for external monitoring tools. The log is written on a background thread.
+    #[id = "log-meters"]
+    pub log_meters: BoolParam,
+
+    #[persist = "morph-slots"]
+    pub morph_slots: MorphSlots,
+
+    /// Where the editor's morph slider sits between slot A at 0 and slot B at 1. Only the editor
+    /// can set the other parameters, so this isn't a parameter the host could automate.
+    #[persist = "morph-position"]
+    pub morph_position: AtomicF32,
+
     #[persist = "editor-state"]
     editor_state: Arc<ViziaState>,
 
@@ -40,8 +675,81 @@ impl Default for Distortion {
     fn default() -> Self {
         Self {
             params: Arc::new(DistortionParams::default()),
-            peak_meter_decay_weight: 1.0,
-            peak_meter: Arc::new(AtomicF32::new(util::MINUS_INFINITY_DB)),
+            meter_coefficients: MeterCoefficients::default(),
+            meter_ballistics: MeterBallistics::Digital,
+            meter_release_weight: 1.0,
+            peak_meters: Arc::new(PeakMeters::default()),
+            session_info: Arc::new(SessionInfo::default()),
+            peak_hold_weight: 1.0,
+            dc_offset_weight: 1.0,
+            distortion_weight: 1.0,
+
+            sample_rate: 1.0,
+
+            brickwall_crossfade: ModeCrossfade::new(false, 0),
+
+            poly_drive: PolyDriveModulation::default(),
+
+            envelopes: Vec::new(),
+            noise_generators: Vec::new(),
+            hum_generator: HumGenerator::default(),
+            test_generator: TestGenerator::default(),
+
+            stereo_analyzer: StereoAnalyzer::default(),
+            stereo_warnings: Arc::new(StereoWarnings::default()),
+
+            limiter: LookaheadLimiter::default(),
+            limiter_enabled: false,
+
+            aux_frame: Vec::new(),
+            aux_wow_flutter: WowFlutter::default(),
+            aux_delay: FrameDelay::default(),
+
+            level_match: Arc::new(LevelMatch::default()),
+            level_measurement: LevelMeasurement::default(),
+
+            transient_detectors: Vec::new(),
+            transient_release_ms: 0.0,
+            transient_lookahead: FrameDelay::default(),
+            transient_lookahead_frame: Vec::new(),
+
+            band_filters: Vec::new(),
+            band_settings: (0.0, 0.0),
+            emphasis_filters: Vec::new(),
+            emphasis_settings: (0.0, 0.0),
+
+            slew_limiters: Vec::new(),
+            drive_compensation: DriveCompensation::default(),
+            compressor: Compressor::default(),
+            wet_compressors: Vec::new(),
+            thermal: ThermalState::default(),
+            sibilance_clippers: Vec::new(),
+            grunge_floors: Vec::new(),
+
+            dc_blockers: Vec::new(),
+            split_dc_blockers: Vec::new(),
+            ceiling_follower: CeilingFollower::default(),
+
+            output_utilities: Arc::new(OutputUtilities::default()),
+            utility_processor: UtilityProcessor::default(),
+            reset_fade: ResetFade::default(),
+
+            silent_samples: 0,
+            meter_logger: MeterLogger::default(),
+
+            wow_flutter: WowFlutter::default(),
+            analog_drift: AnalogDrift::default(),
+            tempo_gate: TempoGate::default(),
+
+            gain_reduction: Arc::new(GainReductionHistory::default()),
+            gain_reduction_recorder: GainReductionRecorder::default(),
+
+            wet_eq: Vec::new(),
+            transient_reference_eq: Vec::new(),
+            dry_eq: Vec::new(),
+
+            dry_frame: Vec::new(),
+            clip_frame: Vec::new(),
         }
     }
 }
@@ -63,6 +771,34 @@ impl Default for DistortionParams {
             .with_unit(" dB")
             .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
             .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+            split_thresholds: BoolParam::new("Split Thresholds", false),
+            negative_threshold: FloatParam::new(
+                "Negative Threshold",
+                0.5,
+                FloatRange::Skewed {
+                    min: 0.001,
+                    max: 1.0,
+                    factor: 1.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+            auto_threshold: BoolParam::new("Auto Threshold", false),
+            auto_threshold_offset: FloatParam::new(
+                "Auto Threshold Offset",
+                util::db_to_gain(-6.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(-24.0),
+                    max: util::db_to_gain(0.0),
+                    factor: FloatRange::gain_skew_factor(-24.0, 0.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
 
             mix: FloatParam::new(
                 "Mix",
@@ -75,144 +811,1700 @@ impl Default for DistortionParams {
                     factor: 1.0,
                 },
             )
-            // Because the gain parameter is stored as linear gain instead of storing the value as
-            // dec ibels, we need logarithmic smoothing
-,
-        }
-    }
-}
-
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
 
-impl Plugin for Distortion {
-    const NAME: &'static str = "distortion_plugin";
-    const VENDOR: &'static str = "Marley Wallace";
-    const URL: &'static str = env!("CARGO_PKG_HOMEPAGE");
-    const EMAIL: &'static str = "reyaw@protonmail.com";
+            // Linear is the default to stay compatible with older sessions
+            mix_law: EnumParam::new("Mix Law", MixLaw::Linear),
 
-    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+            wet_low_cut: eq_cutoff_param("Wet Low Cut", EQ_MIN_HZ),
+            wet_high_cut: eq_cutoff_param("Wet High Cut", EQ_MAX_HZ),
+            dry_low_cut: eq_cutoff_param("Dry Low Cut", EQ_MIN_HZ),
+            dry_high_cut: eq_cutoff_param("Dry High Cut", EQ_MAX_HZ),
+            dry_low_cut_slope: EnumParam::new("Dry Low Cut Slope", LowCutSlope::Medium),
 
-    // The first audio IO layout is used as the default. The other layouts may be selected either
-    // explicitly or automatically by the host or the user depending on the plugin API/backend.
-    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[AudioIOLayout {
-        main_input_channels: NonZeroU32::new(2),
-        main_output_channels: NonZeroU32::new(2),
+            crossfeed: FloatParam::new("Crossfeed", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
 
-        aux_input_ports: &[],
-        aux_output_ports: &[],
+            intensity: FloatParam::new("Intensity", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
 
-        // Individual ports and the layout as a whole can be named here. By default these names
-        // are generated as needed. This layout will be called 'Stereo', while a layout with
-        // only one input and output channel would be called 'Mono'.
-        names: PortNames::const_default(),
-    }];
+            drive: FloatParam::new(
+                "Drive",
+                util::db_to_gain(0.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(0.0),
+                    max: util::db_to_gain(DRIVE_MAX_DB),
+                    factor: FloatRange::gain_skew_factor(0.0, DRIVE_MAX_DB),
+                },
+            )
+            .with_poly_modulation_id(DRIVE_POLY_MOD_ID)
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
 
+            drive_taper: EnumParam::new("Drive Taper", DriveTaper::Linear),
+            drive_compensation: BoolParam::new("Drive Compensation", false),
 
-    const MIDI_INPUT: MidiConfig = MidiConfig::None;
-    const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
+            brickwall: BoolParam::new("Brickwall", false),
+            mode_crossfade: FloatParam::new(
+                "Mode Crossfade",
+                MODE_CROSSFADE_MS,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: MAX_MODE_CROSSFADE_MS,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" ms"),
+            stereo_link: BoolParam::new("Stereo Link", false),
 
-    const SAMPLE_ACCURATE_AUTOMATION: bool = true;
+            even_harmonics: FloatParam::new(
+                "Even Harmonics",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
 
-    // If the plugin can send or receive SysEx messages, it can define a type to wrap around those
-    // messages here. The type implements the `SysExMessage` trait, which allows conversion to and
-    // from plain byte buffers.
-    type SysExMessage = ();
-    // More advanced plugins can use this to run expensive background tasks. See the field's
-    // documentation for more information. `()` means that the plugin does not have any background
-    // tasks.
-    type BackgroundTask = ();
+            knee: FloatParam::new("Knee", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            reclip: IntParam::new(
+                "Re-Clip",
+                1,
+                IntRange::Linear {
+                    min: 1,
+                    max: MAX_RECLIP_PASSES,
+                },
+            )
+            .with_unit("x"),
 
-    fn params(&self) -> Arc<dyn Params> {
-        self.params.clone()
-    }
+            deadband: FloatParam::new(
+                "Deadband",
+                0.0,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: 0.5,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
 
-    fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
-        editor::create(
-            self.params.clone(),
-            self.peak_meter.clone(),
-            self.params.editor_state.clone(),
-        )
-    }
+            thermal: FloatParam::new("Thermal", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            grunge_floor: FloatParam::new(
+                "Grunge Floor",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
 
-    fn initialize(
-        &mut self,
-        _audio_io_layout: &AudioIOLayout,
-        _buffer_config: &BufferConfig,
-        _context: &mut impl InitContext<Self>,
-    ) -> bool {
-        // Resize buffers and perform other potentially expensive initialization operations here.
-        // The `reset()` function is always called right after this function. You can remove this
-        // function if you do not need it.
-        self.peak_meter_decay_weight = 0.25f64
-        .powf((_buffer_config.sample_rate as f64 * PEAK_METER_DECAY_MS / 1000.0).recip())
-        as f32;
-        true
-    }
+            slew_limit: BoolParam::new("Slew Limit", false),
+            slew_rate: FloatParam::new(
+                "Slew Rate",
+                2.0,
+                FloatRange::Skewed {
+                    min: 0.05,
+                    max: 20.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_step_size(0.01)
+            .with_unit(" /ms"),
 
-    fn reset(&mut self) {
-        // Reset buffers and envelopes here. This can be called from the audio thread and may not
-        // allocate. You can remove this function if you do not need it.
-    }
+            instant: BoolParam::new("Instant Response", false),
 
-    // CURRENT PROBLEMS:
-    // 1: positive side of the threshold slider seemingly does nothing
-    // 2: Mix slider should be 0 to 1 instead of -1 to 1
-    // 4: Threshold should be in decibels for sure
+            output: FloatParam::new(
+                "Output",
+                util::db_to_gain(0.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(-24.0),
+                    max: util::db_to_gain(24.0),
+                    factor: FloatRange::gain_skew_factor(-24.0, 24.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
 
-    fn process(
-        &mut self,
-        buffer: &mut Buffer,
-        _aux: &mut AuxiliaryBuffers,
-        _context: &mut impl ProcessContext<Self>,
-    ) -> ProcessStatus {
-        for channel_samples in buffer.iter_samples() {
-            // Smoothing is optionally built into the parameters themselves
+            compressor: BoolParam::new("Compressor", false),
+            compressor_threshold: FloatParam::new(
+                "Comp Threshold",
+                util::db_to_gain(-18.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(-60.0),
+                    max: util::db_to_gain(0.0),
+                    factor: FloatRange::gain_skew_factor(-60.0, 0.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+            compressor_ratio: FloatParam::new(
+                "Comp Ratio",
+                4.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 20.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_step_size(0.1)
+            .with_unit(":1"),
+            compressor_attack: FloatParam::new(
+                "Comp Attack",
+                10.0,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 100.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" ms"),
+            compressor_release: FloatParam::new(
+                "Comp Release",
+                100.0,
+                FloatRange::Skewed {
+                    min: 10.0,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" ms"),
+            compressor_makeup: FloatParam::new(
+                "Comp Makeup",
+                util::db_to_gain(0.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(0.0),
+                    max: util::db_to_gain(24.0),
+                    factor: FloatRange::gain_skew_factor(0.0, 24.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+
+            wet_compressor: BoolParam::new("Wet Compressor", false),
+            wet_compressor_threshold: FloatParam::new(
+                "Wet Comp Threshold",
+                util::db_to_gain(-12.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(-60.0),
+                    max: util::db_to_gain(0.0),
+                    factor: FloatRange::gain_skew_factor(-60.0, 0.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+            wet_compressor_ratio: FloatParam::new(
+                "Wet Comp Ratio",
+                2.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 20.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_step_size(0.1)
+            .with_unit(":1"),
+            wet_compressor_attack: FloatParam::new(
+                "Wet Comp Attack",
+                10.0,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 100.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" ms"),
+            wet_compressor_release: FloatParam::new(
+                "Wet Comp Release",
+                100.0,
+                FloatRange::Skewed {
+                    min: 10.0,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" ms"),
+            wet_compressor_makeup: FloatParam::new(
+                "Wet Comp Makeup",
+                util::db_to_gain(0.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(0.0),
+                    max: util::db_to_gain(24.0),
+                    factor: FloatRange::gain_skew_factor(0.0, 24.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(2))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+
+            transient_clip: BoolParam::new("Transient Clip", false),
+            transient_sensitivity: FloatParam::new(
+                "Transient Sensitivity",
+                0.5,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            transient_release: FloatParam::new(
+                "Transient Release",
+                50.0,
+                FloatRange::Skewed {
+                    min: 10.0,
+                    max: 500.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" ms"),
+            // These change the plugin's latency, so they can't be automated
+            transient_lookahead: BoolParam::new("Transient Look-Ahead", false).non_automatable(),
+            transient_lookahead_time: FloatParam::new(
+                "Transient Look-Ahead Time",
+                1.0,
+                FloatRange::Linear {
+                    min: 0.1,
+                    max: MAX_TRANSIENT_LOOKAHEAD_MS,
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" ms")
+            .non_automatable(),
+
+            emphasis: FloatParam::new("Emphasis", 0.0, FloatRange::Linear { min: 0.0, max: 18.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_step_size(0.1)
+                .with_unit(" dB"),
+            emphasis_pivot: FloatParam::new(
+                "Emphasis Pivot",
+                2000.0,
+                FloatRange::Skewed {
+                    min: 200.0,
+                    max: 10_000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+
+            band_mode: BoolParam::new("Band Mode", false),
+            band_solo: EnumParam::new("Band Solo", BandSolo::Off).non_automatable(),
+
+            sibilance_sensitivity: FloatParam::new(
+                "De-Ess",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            sibilance_frequency: FloatParam::new(
+                "De-Ess Frequency",
+                6000.0,
+                FloatRange::Skewed {
+                    min: 2000.0,
+                    max: 16_000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+
+            band_center: FloatParam::new(
+                "Band Center",
+                1000.0,
+                FloatRange::Skewed {
+                    min: 20.0,
+                    max: 20_000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+            band_width: FloatParam::new(
+                "Band Width",
+                1.0,
+                FloatRange::Linear { min: 0.1, max: 4.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_step_size(0.01)
+            .with_unit(" oct"),
+
+            noise: FloatParam::new("Noise", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            hum: EnumParam::new("Hum", HumFrequency::Off),
+
+            test_signal: EnumParam::new("Test Signal", TestSignal::Off).non_automatable(),
+            test_frequency: FloatParam::new(
+                "Test Frequency",
+                440.0,
+                FloatRange::Skewed {
+                    min: 20.0,
+                    max: 20_000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+            test_level: FloatParam::new(
+                "Test Level",
+                util::db_to_gain(-18.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(-60.0),
+                    max: util::db_to_gain(0.0),
+                    factor: FloatRange::gain_skew_factor(-60.0, 0.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(1))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+
+            wow_depth: FloatParam::new("Wow Depth", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            wow_rate: FloatParam::new("Wow Rate", 0.8, FloatRange::Linear { min: 0.1, max: 4.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_step_size(0.01)
+                .with_unit(" Hz"),
+            flutter_depth: FloatParam::new(
+                "Flutter Depth",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            flutter_rate: FloatParam::new(
+                "Flutter Rate",
+                8.0,
+                FloatRange::Linear {
+                    min: 4.0,
+                    max: 20.0,
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_step_size(0.01)
+            .with_unit(" Hz"),
+            wow_interpolation: EnumParam::new("Interpolation", DelayInterpolation::Linear),
+
+            drift: FloatParam::new("Analog Drift", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_smoother(SmoothingStyle::Linear(50.0))
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            gate: BoolParam::new("Gate", false),
+            gate_division: EnumParam::new("Gate Division", GateDivision::Sixteenth),
+            gate_rate: FloatParam::new(
+                "Gate Rate",
+                8.0,
+                FloatRange::Skewed {
+                    min: 0.5,
+                    max: 32.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_step_size(0.01)
+            .with_unit(" Hz"),
+            gate_shape: EnumParam::new("Gate Shape", GateShape::Hard),
+            gate_depth: FloatParam::new(
+                "Gate Depth",
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_smoother(SmoothingStyle::Linear(50.0))
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            gate_smoothing: FloatParam::new(
+                "Gate Smoothing",
+                2.0,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: 50.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" ms"),
+
+            meter_ballistics: EnumParam::new("Meter Ballistics", MeterBallistics::Digital)
+                .non_automatable(),
+            meter_source: EnumParam::new("Meter Source", MeterSource::Output).non_automatable(),
+            meter_decay: MeterDecay::default(),
+            meter_per_channel: AtomicBool::new(false),
+            meter_refresh_hz: AtomicU32::new(METER_REFRESH_RATES_HZ[0]),
+            gain_staging_guide: AtomicBool::new(false),
+            wet_chain: WetChain::default(),
+
+            // Both of these change the plugin's latency, so they can't be automated
+            limiter: BoolParam::new("Limiter", false).non_automatable(),
+            limiter_lookahead: FloatParam::new(
+                "Lookahead",
+                2.0,
+                FloatRange::Linear {
+                    min: 1.0,
+                    max: MAX_LOOKAHEAD_MS,
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" ms")
+            .non_automatable(),
+            zero_latency: BoolParam::new("Zero Latency", false).non_automatable(),
+            auto_bypass: BoolParam::new("Auto Bypass", false),
+            safety_clip: BoolParam::new("Safety Clip", true),
+            internal_ceiling: FloatParam::new(
+                "Internal Ceiling",
+                util::db_to_gain(48.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(12.0),
+                    max: util::db_to_gain(96.0),
+                    factor: FloatRange::gain_skew_factor(12.0, 96.0),
+                },
+            )
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_gain_to_db(0))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db())
+            .non_automatable(),
+            double_precision: BoolParam::new("64-bit Filters", false).non_automatable(),
+            log_meters: BoolParam::new("Log Meters", false).non_automatable(),
+
+            morph_slots: MorphSlots::default(),
+            morph_position: AtomicF32::new(0.0),
+        }
+    }
+}
+
+
+impl Plugin for Distortion {
+    const NAME: &'static str = "distortion_plugin";
+    const VENDOR: &'static str = "Marley Wallace";
+    const URL: &'static str = env!("CARGO_PKG_HOMEPAGE");
+    const EMAIL: &'static str = "reyaw@protonmail.com";
+
+    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+    // The first audio IO layout is used as the default. The other layouts may be selected either
+    // explicitly or automatically by the host or the user depending on the plugin API/backend.
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+
+            aux_input_ports: &[],
+            // Always carries the fully wet signal, regardless of the mix
+            aux_output_ports: &[new_nonzero_u32(2)],
+
+            names: PortNames {
+                aux_outputs: &["Distortion Only"],
+                ..PortNames::const_default()
+            },
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+
+            // Individual ports and the layout as a whole can be named here. By default these
+            // names are generated as needed. This layout will be called 'Stereo', while a layout
+            // with only one input and output channel would be called 'Mono'.
+            names: PortNames::const_default(),
+        },
+    ];
 
-            let mut amplitude = 0.0;
-            let num_samples = channel_samples.len();
 
-            let threshold = self.params.threshold.smoothed.next();
-            let mix = self.params.mix.smoothed.next();
+    // Note events are needed to receive CLAP polyphonic modulation for the drive
+    const MIDI_INPUT: MidiConfig = MidiConfig::Basic;
+    const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
+
+    const SAMPLE_ACCURATE_AUTOMATION: bool = true;
+
+    // If the plugin can send or receive SysEx messages, it can define a type to wrap around those
+    // messages here. The type implements the `SysExMessage` trait, which allows conversion to and
+    // from plain byte buffers.
+    type SysExMessage = ();
+    // Expensive or blocking work is posted to nih-plug's background thread as these tasks
+    type BackgroundTask = Task;
+
+    fn params(&self) -> Arc<dyn Params> {
+        self.params.clone()
+    }
+
+    fn task_executor(&mut self) -> TaskExecutor<Self> {
+        Box::new(task::execute)
+    }
+
+    fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
+        editor::create(
+            self.params.clone(),
+            self.peak_meters.clone(),
+            self.stereo_warnings.clone(),
+            self.level_match.clone(),
+            self.output_utilities.clone(),
+            self.gain_reduction.clone(),
+            self.session_info.clone(),
+        )
+    }
+
+    fn initialize(
+        &mut self,
+        audio_io_layout: &AudioIOLayout,
+        buffer_config: &BufferConfig,
+        context: &mut impl InitContext<Self>,
+    ) -> bool {
+        // Resize buffers and perform other potentially expensive initialization operations here.
+        // The `reset()` function is always called right after this function. You can remove this
+        // function if you do not need it.
+        self.sample_rate = buffer_config.sample_rate;
+        self.session_info.update(buffer_config);
+
+        self.params.meter_decay.set_sample_rate(self.sample_rate);
+        self.meter_ballistics = self.params.meter_ballistics.value();
+        self.meter_release_weight = self.params.meter_decay.release_weight();
+        self.meter_coefficients = MeterCoefficients::new(
+            self.meter_ballistics,
+            self.sample_rate,
+            &self.params.meter_decay,
+        );
+        self.peak_hold_weight = peak_hold_fall_weight(self.sample_rate);
+        self.dc_offset_weight = dc_offset_weight(self.sample_rate);
+        self.distortion_weight = distortion_weight(self.sample_rate);
+
+        self.brickwall_crossfade = ModeCrossfade::new(
+            self.params.brickwall.value(),
+            self.mode_crossfade_samples(),
+        );
+
+        let num_channels = audio_io_layout
+            .main_output_channels
+            .map(NonZeroU32::get)
+            .unwrap_or(0) as usize;
+
+        self.envelopes = vec![
+            EnvelopeFollower::new(self.sample_rate, NOISE_GATE_ATTACK_MS, NOISE_GATE_RELEASE_MS);
+            num_channels
+        ];
+        // Fixed seeds keep renders reproducible while still decorrelating the channels
+        self.noise_generators = (0..num_channels as u32)
+            .map(|channel_idx| {
+                NoiseGenerator::new(0x9E37_79B9 ^ (channel_idx + 1).wrapping_mul(0x85EB_CA6B))
+            })
+            .collect();
+        self.stereo_analyzer = StereoAnalyzer::new(self.sample_rate);
+
+        self.transient_release_ms = self.params.transient_release.value();
+        self.transient_detectors =
+            vec![TransientDetector::new(self.sample_rate, self.transient_release_ms); num_channels];
+        let max_transient_lookahead =
+            (MAX_TRANSIENT_LOOKAHEAD_MS / 1000.0 * self.sample_rate).ceil() as usize;
+        self.transient_lookahead = FrameDelay::new(num_channels, max_transient_lookahead);
+        self.transient_lookahead
+            .set_delay(self.transient_lookahead_samples());
+        self.transient_lookahead_frame = vec![0.0; num_channels];
+
+        // The coefficients are filled in on the first sample
+        self.band_filters = vec![Biquad::default(); num_channels];
+        self.band_settings = (0.0, 0.0);
+        self.emphasis_filters = vec![(Biquad::default(), Biquad::default()); num_channels];
+        self.emphasis_settings = (0.0, 0.0);
+
+        self.dc_blockers = vec![DcBlocker::new(self.sample_rate); num_channels];
+        self.split_dc_blockers = vec![DcBlocker::new(self.sample_rate); num_channels];
+        self.ceiling_follower = CeilingFollower::new(self.sample_rate);
+        self.slew_limiters = vec![SlewLimiter::default(); num_channels];
+        self.drive_compensation = DriveCompensation::new(self.sample_rate);
+        // The envelope times are filled in on the first sample
+        self.compressor = Compressor::default();
+        self.wet_compressors = vec![Compressor::default(); num_channels];
+        self.thermal = ThermalState::new(self.sample_rate);
+        self.sibilance_clippers = vec![SibilanceClipper::new(self.sample_rate); num_channels];
+        self.grunge_floors = vec![GrungeFloor::new(self.sample_rate); num_channels];
+
+        self.wow_flutter = WowFlutter::new(num_channels, self.sample_rate);
+        self.analog_drift = AnalogDrift::new(self.sample_rate);
+        // The smoothing coefficient depends on the sample rate, it's filled in on the first buffer
+        self.tempo_gate = TempoGate::default();
+        self.gain_reduction_recorder = GainReductionRecorder::new(self.sample_rate);
+        self.meter_logger = MeterLogger::new(self.sample_rate);
+
+        // The coefficients are filled in on the first sample
+        self.wet_eq = vec![PathEq::default(); num_channels];
+        self.transient_reference_eq = vec![PathEq::default(); num_channels];
+        self.dry_eq = vec![PathEq::default(); num_channels];
+        self.dry_frame = vec![(0.0, 0.0); num_channels];
+        self.clip_frame = vec![ClippedSample::default(); num_channels];
+
+        self.limiter = LookaheadLimiter::new(num_channels, self.sample_rate);
+        self.limiter_enabled = self.params.limiter.value();
+        self.limiter.set_lookahead(self.limiter_lookahead_samples());
+        context.set_latency_samples(self.latency_samples());
+
+        let max_lookahead = (MAX_LOOKAHEAD_MS / 1000.0 * self.sample_rate).ceil() as usize;
+        self.aux_frame = vec![0.0; num_channels];
+        self.aux_wow_flutter = WowFlutter::new(num_channels, self.sample_rate);
+        self.aux_delay = FrameDelay::new(num_channels, max_lookahead);
+        self.aux_delay.set_delay(self.limiter_latency());
+
+        true
+    }
+
+    fn reset(&mut self) {
+        // Reset buffers and envelopes here. This can be called from the audio thread and may not
+        // allocate. You can remove this function if you do not need it.
+        for envelope in &mut self.envelopes {
+            envelope.reset();
+        }
+        self.hum_generator.reset();
+        self.test_generator.reset();
+        self.stereo_analyzer.reset();
+        self.brickwall_crossfade.reset(self.params.brickwall.value());
+        self.poly_drive.reset();
+        self.limiter.reset();
+        for detector in &mut self.transient_detectors {
+            detector.reset();
+        }
+        self.transient_lookahead.reset();
+        for filter in &mut self.band_filters {
+            filter.reset();
+        }
+        for (emphasis, de_emphasis) in &mut self.emphasis_filters {
+            emphasis.reset();
+            de_emphasis.reset();
+        }
+        for dc_blocker in self.dc_blockers.iter_mut().chain(&mut self.split_dc_blockers) {
+            dc_blocker.reset();
+        }
+        for slew_limiter in &mut self.slew_limiters {
+            slew_limiter.reset();
+        }
+        self.drive_compensation
+            .reset(self.params.drive_compensation.value());
+        self.compressor.reset();
+        self.ceiling_follower.reset();
+        for wet_compressor in &mut self.wet_compressors {
+            wet_compressor.reset();
+        }
+        self.thermal.reset();
+        for sibilance_clipper in &mut self.sibilance_clippers {
+            sibilance_clipper.reset();
+        }
+        for grunge_floor in &mut self.grunge_floors {
+            grunge_floor.reset();
+        }
+        self.utility_processor.reset(&self.output_utilities);
+        self.silent_samples = 0;
+        self.meter_logger.reset();
+        self.wow_flutter.reset();
+        self.aux_wow_flutter.reset();
+        self.aux_delay.reset();
+        self.analog_drift.reset();
+        self.tempo_gate.reset();
+        for eq in self
+            .wet_eq
+            .iter_mut()
+            .chain(&mut self.dry_eq)
+            .chain(&mut self.transient_reference_eq)
+        {
+            eq.reset();
+        }
+    }
+
+    // CURRENT PROBLEMS:
+    // 1: positive side of the threshold slider seemingly does nothing
+    // 2: Mix slider should be 0 to 1 instead of -1 to 1
+    // 4: Threshold should be in decibels for sure
 
-            for sample in channel_samples {
-                let mut output = sample.clone();
-                let clean_out = sample.clone();
-                //Split these up for positive and negative input values?????
+    fn process(
+        &mut self,
+        buffer: &mut Buffer,
+        aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        // The limiter's latency can only change between buffers
+        let limiter_enabled = self.params.limiter.value();
+        let limiter_lookahead = self.limiter_lookahead_samples();
+        if limiter_enabled != self.limiter_enabled || limiter_lookahead != self.limiter.lookahead() {
+            self.limiter_enabled = limiter_enabled;
+            self.limiter.set_lookahead(limiter_lookahead);
+            self.aux_delay.set_delay(self.limiter_latency());
+            context.set_latency_samples(self.latency_samples());
+        }
+        self.brickwall_crossfade
+            .set_length(self.mode_crossfade_samples());
+        let transient_lookahead = self.transient_lookahead_samples();
+        if transient_lookahead != self.transient_lookahead.delay() {
+            self.transient_lookahead.set_delay(transient_lookahead);
+            context.set_latency_samples(self.latency_samples());
+        }
 
-                if output > threshold {
-                   output = threshold;
-                  // input = threshold + (1.0/(input-threshold));
+        // The digital release weight is precomputed by the editor, so picking up changes to it is
+        // just an atomic load
+        let meter_ballistics = self.params.meter_ballistics.value();
+        let meter_release_weight = self.params.meter_decay.release_weight();
+        if meter_ballistics != self.meter_ballistics
+            || meter_release_weight != self.meter_release_weight
+        {
+            self.meter_ballistics = meter_ballistics;
+            self.meter_release_weight = meter_release_weight;
+            self.meter_coefficients = MeterCoefficients::new(
+                meter_ballistics,
+                self.sample_rate,
+                &self.params.meter_decay,
+            );
+        }
+
+        self.level_measurement.poll_request(&self.level_match, self.sample_rate);
+
+        self.utility_processor.update(&self.output_utilities, self.sample_rate);
+        self.reset_fade.update(&self.output_utilities, self.sample_rate);
+
+        let transient_release_ms = self.params.transient_release.value();
+        if transient_release_ms != self.transient_release_ms {
+            self.transient_release_ms = transient_release_ms;
+            for detector in &mut self.transient_detectors {
+                detector.set_release(self.sample_rate, transient_release_ms);
+            }
+        }
+
+        // The test signal replaces the input before anything looks at it, the auto bypass included
+        let test_signal = self.params.test_signal.value();
+        if test_signal != TestSignal::Off {
+            for mut channel_samples in buffer.iter_samples() {
+                let frequency = self.params.test_frequency.smoothed.next();
+                let level = self.params.test_level.smoothed.next();
+                let sample =
+                    self.test_generator.next(test_signal, frequency, self.sample_rate) * level;
+                for channel_sample in channel_samples.iter_mut() {
+                    *channel_sample = sample;
                 }
-                else if output < -threshold {
-                    output = -threshold;
-                  // input = -threshold - (1.0/(input-threshold));
+            }
+        }
+
+        // Garbage from the host or an upstream plugin would otherwise end up in the filters and
+        // envelopes and never go away again
+        sanitize_buffer(buffer);
+
+        // The whole buffer is checked before anything is skipped, so processing resumes on the
+        // very first sample that isn't silent
+        if buffer_peak(buffer) < AUTO_BYPASS_THRESHOLD {
+            self.silent_samples = self.silent_samples.saturating_add(buffer.samples());
+        } else {
+            self.silent_samples = 0;
+        }
+        let auto_bypass_hold = (AUTO_BYPASS_HOLD_MS / 1000.0 * self.sample_rate) as usize;
+        if self.params.auto_bypass.value() && self.silent_samples > auto_bypass_hold {
+            return self.process_silence(buffer, aux, context);
+        }
+
+        // The host may not connect the aux output, or use the layout without it
+        let mut wet_output = aux.outputs.first_mut().map(|buffer| buffer.as_slice());
+        self.tempo_gate.sync(
+            context.transport(),
+            self.params.gate_division.value(),
+            self.sample_rate,
+        );
+        self.tempo_gate
+            .set_smoothing(self.sample_rate, self.params.gate_smoothing.value());
+        let mut next_event = context.next_event();
+        for (sample_idx, mut channel_samples) in buffer.iter_samples().enumerate() {
+            while let Some(event) = next_event {
+                if event.timing() > sample_idx as u32 {
+                    break;
+                }
+
+                self.poly_drive.handle_event(&event, self.sample_rate);
+                next_event = context.next_event();
+            }
+
+            // The reset happens once the output has faded out completely
+            let (reset_gain, clear_state) = self.reset_fade.next(self.sample_rate);
+            if clear_state {
+                self.reset();
+            }
+
+            // Smoothing is optionally built into the parameters themselves
+
+            let num_samples = channel_samples.len();
+
+            let ProcessedFrame {
+                input_frame,
+                clipper_gain,
+                distortion_frame,
+                driven_peak,
+            } = self.process_frame(&mut channel_samples);
+            let safety_clip = self.params.safety_clip.value();
+
+            if let (Some(input_frame), Some(output_frame)) =
+                (input_frame, stereo_frame(&mut channel_samples))
+            {
+                self.stereo_analyzer.process(input_frame, output_frame);
+            }
+            // These are for monitoring, so they come after the stereo analysis
+            self.utility_processor.process(&mut channel_samples);
+            if safety_clip {
+                for sample in channel_samples.iter_mut() {
+                    *sample = knee_clip(*sample, SAFETY_CLIP_CEILING, SAFETY_CLIP_KNEE);
                 }
-                // Wet/dry basically
-                // Combine distorted signal with original based on mix
-                *sample = ((1.0-mix) * clean_out) + (mix * output);
             }
+            // Last line of defense, nothing that isn't a number should ever leave the plugin
+            for sample in channel_samples.iter_mut() {
+                *sample = finite_or_zero(*sample * reset_gain);
+            }
+            for (channel_idx, wet_sample) in self.aux_frame.iter().enumerate() {
+                let wet_sample = if safety_clip {
+                    knee_clip(*wet_sample, SAFETY_CLIP_CEILING, SAFETY_CLIP_KNEE)
+                } else {
+                    *wet_sample
+                };
+                if let Some(sample) = wet_output
+                    .as_mut()
+                    .and_then(|channels| channels.get_mut(channel_idx))
+                    .and_then(|channel| channel.get_mut(sample_idx))
+                {
+                    *sample = finite_or_zero(wet_sample * reset_gain);
+                }
+            }
+
+            // The log itself is written on the background thread
+            if self.params.log_meters.value() {
+                let output_peak = channel_samples
+                    .iter_mut()
+                    .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+                let snapshot =
+                    self.meter_logger
+                        .add_frame(output_peak, clipper_gain.min(1.0), distortion_frame);
+                if let Some(snapshot) = snapshot {
+                    context.execute_background(Task::LogMeters(snapshot));
+                }
+            }
+
             // To save resources, a plugin can (and probably should!) only perform expensive
             // calculations that are only displayed on the GUI while the GUI is open
             if self.params.editor_state.is_open() {
-                amplitude = (amplitude / num_samples as f32).abs();
-                let current_peak_meter = self.peak_meter.load(std::sync::atomic::Ordering::Relaxed);
-                let new_peak_meter = if amplitude > current_peak_meter {
-                    amplitude
+                self.gain_reduction_recorder
+                    .add_frame(clipper_gain.min(1.0), &self.gain_reduction);
+
+                // The clip frame still holds this frame's driven and clipped samples
+                let meter_source = self.params.meter_source.value();
+                let meter_sample = |channel_idx: usize, sample: f32| {
+                    let clipped = &self.clip_frame[channel_idx];
+                    meter_source.select(clipped.driven, clipped.output, sample)
+                };
+                let amplitude: f32 = channel_samples
+                    .iter_mut()
+                    .enumerate()
+                    .map(|(channel_idx, sample)| meter_sample(channel_idx, *sample).abs())
+                    .sum();
+                // Every channel is rectified on its own, so anti-phase channels don't cancel out
+                let amplitude = amplitude / num_samples as f32;
+                let current_peak_meter =
+                    self.peak_meters.summed.load(std::sync::atomic::Ordering::Relaxed);
+                let new_peak_meter = self.meter_coefficients.apply(current_peak_meter, amplitude);
+
+                self.peak_meters
+                    .summed
+                    .store(new_peak_meter, std::sync::atomic::Ordering::Relaxed);
+
+                let channel_meters = self.peak_meters.channels.iter();
+                for (channel_idx, (meter, sample)) in
+                    channel_meters.zip(channel_samples.iter_mut()).enumerate()
+                {
+                    let current = meter.load(std::sync::atomic::Ordering::Relaxed);
+                    let sample = meter_sample(channel_idx, *sample);
+                    let new = self.meter_coefficients.apply(current, sample.abs());
+                    meter.store(new, std::sync::atomic::Ordering::Relaxed);
+                }
+                let sample_peak = channel_samples
+                    .iter_mut()
+                    .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+                self.peak_meters.hold(sample_peak, self.peak_hold_weight);
+                self.peak_meters
+                    .hold_driven(driven_peak, self.peak_hold_weight);
+
+                let (driven_power, distortion_power) = distortion_frame;
+                let distortion = if driven_power > f32::EPSILON {
+                    (distortion_power / driven_power).sqrt().min(1.0)
                 } else {
-                    current_peak_meter * self.peak_meter_decay_weight
-                        + amplitude * (1.0 - self.peak_meter_decay_weight)
+                    0.0
                 };
+                self.peak_meters
+                    .track_distortion(distortion, self.distortion_weight);
 
-                self.peak_meter
-                    .store(new_peak_meter, std::sync::atomic::Ordering::Relaxed)
+                // Every channel has its own DC blocker, so this shows whether one of them ends
+                // up with more offset than the other
+                for (channel_idx, sample) in channel_samples.iter_mut().enumerate() {
+                    self.peak_meters
+                        .track_dc(channel_idx, *sample, self.dc_offset_weight);
+                }
             }
 
 
         }
 
+        if self.params.editor_state.is_open() {
+            self.stereo_analyzer.update_warnings(&self.stereo_warnings);
+        }
+
         ProcessStatus::Normal
     }
 }
 
+impl Distortion {
+    /// The limiter's look-ahead in samples, based on the current parameter values.
+    fn limiter_lookahead_samples(&self) -> usize {
+        if self.params.zero_latency.value() {
+            0
+        } else {
+            (self.params.limiter_lookahead.value() / 1000.0 * self.sample_rate).round() as usize
+        }
+    }
+
+    /// The length of the brickwall mode crossfade in samples, based on the current parameter value.
+    fn mode_crossfade_samples(&self) -> usize {
+        (self.params.mode_crossfade.value() / 1000.0 * self.sample_rate).round() as usize
+    }
+
+    /// The transient detectors' look-ahead in samples, based on the current parameter values.
+    fn transient_lookahead_samples(&self) -> usize {
+        if self.params.transient_lookahead.value() && !self.params.zero_latency.value() {
+            (self.params.transient_lookahead_time.value() / 1000.0 * self.sample_rate).round()
+                as usize
+        } else {
+            0
+        }
+    }
+
+    /// Handle a silent buffer while the auto bypass is active. The input is already below -90
+    /// dBFS, so it's passed through as is. Note events still need to be handled so no voices get
+    /// stuck, and the meters fall back down.
+    fn process_silence(
+        &mut self,
+        buffer: &mut Buffer,
+        aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        // There's no distortion to send to the aux output either
+        for channel in aux.outputs.iter_mut().flat_map(|buffer| buffer.as_slice().iter_mut()) {
+            channel.fill(0.0);
+        }
+
+        while let Some(event) = context.next_event() {
+            self.poly_drive.handle_event(&event, self.sample_rate);
+        }
+
+        // Skipped buffers are silent on both sides, so they can count towards the measurement
+        self.level_measurement
+            .add_silence(buffer.samples(), &self.level_match);
+
+        if self.params.editor_state.is_open() {
+            let mut peak_meter = self.peak_meters.summed.load(std::sync::atomic::Ordering::Relaxed);
+            for _ in 0..buffer.samples() {
+                peak_meter = self.meter_coefficients.apply(peak_meter, 0.0);
+                self.peak_meters.hold(0.0, self.peak_hold_weight);
+                self.peak_meters.hold_driven(0.0, self.peak_hold_weight);
+                for channel_idx in 0..self.peak_meters.dc_offsets.len() {
+                    self.peak_meters.track_dc(channel_idx, 0.0, self.dc_offset_weight);
+                }
+                self.peak_meters.track_distortion(0.0, self.distortion_weight);
+                self.gain_reduction_recorder
+                    .add_frame(1.0, &self.gain_reduction);
+            }
+            self.peak_meters
+                .summed
+                .store(peak_meter, std::sync::atomic::Ordering::Relaxed);
+
+            for meter in self.peak_meters.channels.iter() {
+                let mut value = meter.load(std::sync::atomic::Ordering::Relaxed);
+                for _ in 0..buffer.samples() {
+                    value = self.meter_coefficients.apply(value, 0.0);
+                }
+                meter.store(value, std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+
+        ProcessStatus::Normal
+    }
+
+    /// Run a single frame through everything up to and including the limiter and the aux output's
+    /// delay. This is where all of the latency comes from, so the tests use this to check that it
+    /// matches what's reported to the host.
+    fn process_frame(&mut self, channel_samples: &mut ChannelSamples) -> ProcessedFrame {
+        let instant = self.params.instant.value();
+        let intensity = IntensityMapping::new(next_value(&self.params.intensity, instant));
+        let threshold = next_value(&self.params.threshold, instant);
+        let auto_threshold_offset = next_value(&self.params.auto_threshold_offset, instant);
+        let threshold = if self.params.auto_threshold.value() {
+            self.ceiling_follower.threshold(auto_threshold_offset)
+        } else {
+            threshold
+        } * intensity.threshold_gain;
+        let mix = intensity.apply_mix(next_value(&self.params.mix, instant));
+        let (dry_gain, wet_gain) = self.params.mix_law.value().gains(mix);
+        let (drift_ratio, drift_gain) =
+            self.analog_drift.next(next_value(&self.params.drift, instant));
+        let wet_cutoffs = drift_cutoffs(
+            (
+                next_value(&self.params.wet_low_cut, instant),
+                next_value(&self.params.wet_high_cut, instant),
+            ),
+            drift_ratio,
+        );
+        let dry_cutoffs = (
+            next_value(&self.params.dry_low_cut, instant),
+            next_value(&self.params.dry_high_cut, instant),
+        );
+        let dry_low_cut_slope = self.params.dry_low_cut_slope.value();
+        let crossfeed = next_value(&self.params.crossfeed, instant);
+        // The gate keeps running while disabled, so toggling it fades in and out smoothly
+        let gate_depth = if self.params.gate.value() {
+            next_value(&self.params.gate_depth, instant)
+        } else {
+            0.0
+        };
+        let gate_gain = self.tempo_gate.next(
+            next_value(&self.params.gate_rate, instant),
+            gate_depth,
+            self.params.gate_shape.value(),
+            self.sample_rate,
+        );
+        let drive = next_value(&self.params.drive, instant);
+        let drive_offset = self.poly_drive.next_offset();
+        let drive = if drive_offset != 0.0 {
+            self.params.drive.preview_modulated(drive_offset)
+        } else {
+            drive
+        };
+        let drive = self.params.drive_taper.value().apply(drive) * intensity.drive_gain;
+        let output_gain = next_value(&self.params.output, instant);
+
+        let brickwall = self.params.brickwall.value();
+        let brickwall_fade = self.brickwall_crossfade.next(brickwall);
+        let even_harmonics = next_value(&self.params.even_harmonics, instant);
+        let (thermal_gain, knee) = self.thermal.apply(
+            next_value(&self.params.knee, instant),
+            next_value(&self.params.thermal, instant),
+        );
+        let deadband = next_value(&self.params.deadband, instant);
+        let reclip_passes = self.params.reclip.value();
+        let slew_limit = self.params.slew_limit.value();
+        let slew_rate = next_value(&self.params.slew_rate, instant);
+        let sibilance_sensitivity = next_value(&self.params.sibilance_sensitivity, instant);
+        let sibilance_frequency = next_value(&self.params.sibilance_frequency, instant);
+        let grunge_floor = next_value(&self.params.grunge_floor, instant);
+        let clip_threshold = mode_threshold(brickwall, threshold) * thermal_gain;
+        let split_thresholds = self.params.split_thresholds.value();
+        let negative_threshold =
+            next_value(&self.params.negative_threshold, instant) * intensity.threshold_gain;
+        let negative_threshold = if split_thresholds {
+            negative_threshold
+        } else {
+            threshold
+        };
+        let clip_thresholds = (
+            clip_threshold,
+            mode_threshold(brickwall, negative_threshold) * thermal_gain,
+        );
+
+        let transient_clip = self.params.transient_clip.value();
+        let transient_sensitivity = next_value(&self.params.transient_sensitivity, instant);
+
+        let double_precision = self.params.double_precision.value();
+        let internal_ceiling = self.params.internal_ceiling.value();
+        let band_mode = self.params.band_mode.value();
+        let band_solo = if band_mode {
+            self.params.band_solo.value()
+        } else {
+            BandSolo::Off
+        };
+        let band_settings = (
+            next_value(&self.params.band_center, instant),
+            next_value(&self.params.band_width, instant),
+        );
+        if band_settings != self.band_settings {
+            self.band_settings = band_settings;
+            let (center, width) = band_settings;
+            let coefficients = BiquadCoefficients::bandpass(self.sample_rate, center, width);
+            for filter in &mut self.band_filters {
+                filter.set_coefficients(coefficients);
+            }
+        }
+        let emphasis_settings = (
+            next_value(&self.params.emphasis, instant),
+            next_value(&self.params.emphasis_pivot, instant),
+        );
+        if emphasis_settings != self.emphasis_settings {
+            self.emphasis_settings = emphasis_settings;
+            let (emphasis_db, pivot) = emphasis_settings;
+            let coefficients = BiquadCoefficients::high_shelf_first_order(
+                self.sample_rate,
+                pivot,
+                util::db_to_gain(emphasis_db),
+            );
+            for (emphasis, de_emphasis) in &mut self.emphasis_filters {
+                emphasis.set_coefficients(coefficients);
+                de_emphasis.set_coefficients(coefficients.inverse());
+            }
+        }
+
+        let noise_gain = next_value(&self.params.noise, instant) * NOISE_MAX_GAIN;
+        let hum = match self.params.hum.value().frequency() {
+            Some(frequency) => self.hum_generator.next(frequency, self.sample_rate),
+            None => 0.0,
+        };
+
+        let wow = (
+            next_value(&self.params.wow_rate, instant),
+            next_value(&self.params.wow_depth, instant),
+        );
+        let flutter = (
+            next_value(&self.params.flutter_rate, instant),
+            next_value(&self.params.flutter_depth, instant),
+        );
+
+        // Everything after this point runs behind the transient detectors' look-ahead, which
+        // get the undelayed input instead. Without look-ahead this passes the input through.
+        for (channel_idx, sample) in channel_samples.iter_mut().enumerate() {
+            self.transient_lookahead_frame[channel_idx] = *sample;
+        }
+        self.transient_lookahead.process(channel_samples.iter_mut());
+
+        let input_frame = stereo_frame(channel_samples);
+        let mut input_power = 0.0;
+        let mut output_power = 0.0;
+        let mut clipper_gain = 1.0f32;
+        let drive_compensation = self.params.drive_compensation.value();
+        let compensation_gain =
+            self.drive_compensation.next_gain(self.sample_rate, drive_compensation);
+        let mut compensation_frame = (0.0, 0.0, 0.0);
+        // The driven signal's power and the power of what the clipper changed about it
+        let mut distortion_frame = (0.0f32, 0.0f32);
+        let mut driven_peak = 0.0f32;
+
+        // The compressor is linked, so it follows the loudest channel
+        self.compressor.set_times(
+            self.sample_rate,
+            self.params.compressor_attack.value(),
+            self.params.compressor_release.value(),
+        );
+        let frame_peak = channel_samples
+            .iter_mut()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        let compressor_gain = self.compressor.process(
+            frame_peak,
+            next_value(&self.params.compressor_threshold, instant),
+            next_value(&self.params.compressor_ratio, instant),
+        );
+        let compressor_makeup = next_value(&self.params.compressor_makeup, instant);
+        let compressor_gain = if self.params.compressor.value() {
+            compressor_gain * compressor_makeup
+        } else {
+            1.0
+        };
+        let wet_chain = self.params.wet_chain.order();
+        let wet_compressor = self.params.wet_compressor.value();
+        let wet_compressor_times = (
+            self.params.wet_compressor_attack.value(),
+            self.params.wet_compressor_release.value(),
+        );
+        let wet_compressor_threshold =
+            next_value(&self.params.wet_compressor_threshold, instant);
+        let wet_compressor_ratio = next_value(&self.params.wet_compressor_ratio, instant);
+        let wet_compressor_makeup = next_value(&self.params.wet_compressor_makeup, instant);
+
+        for (channel_idx, sample) in channel_samples.iter_mut().enumerate() {
+            let clean_out = *sample;
+
+            // In band mode only the band goes through the clipper, and the rest of the
+            // spectrum is added back afterwards. The rest is whatever the band-pass removed,
+            // so the two sum back to the input when nothing gets clipped.
+            let compressed = clean_out * compressor_gain;
+            let band = self.band_filters[channel_idx].process(compressed, double_precision);
+            let (clip_input, passthrough) = if band_mode {
+                (band, compressed - band)
+            } else {
+                (compressed, 0.0)
+            };
+            // The emphasis is undone after the clipper, so only the distortion changes
+            let (emphasis, _) = &mut self.emphasis_filters[channel_idx];
+            let emphasized = emphasis.process(clip_input, double_precision);
+            let driven = crossover(emphasized * drive, deadband);
+            let driven = limit_internal(driven, internal_ceiling);
+            driven_peak = driven_peak.max(driven.abs());
+
+            let symmetric =
+                reclip(driven, (clip_threshold, clip_threshold), knee, reclip_passes);
+            let symmetric_gain = if driven.abs() > f32::EPSILON {
+                (symmetric / driven).abs().min(1.0)
+            } else {
+                1.0
+            };
+            let mut output = symmetric;
+            if split_thresholds {
+                // Only the difference to the symmetric clipper is DC filtered, so with equal
+                // thresholds this is exactly the same as the regular clipper
+                let split = reclip(driven, clip_thresholds, knee, reclip_passes);
+                output += self.split_dc_blockers[channel_idx]
+                    .process(split - symmetric, double_precision);
+            }
+            let mut asymmetric = asymmetric_clip(driven, clip_threshold, knee);
+            if let Some((previous, previous_weight)) = brickwall_fade {
+                let previous_threshold = mode_threshold(previous, threshold) * thermal_gain;
+                let previous_thresholds = (
+                    previous_threshold,
+                    mode_threshold(previous, negative_threshold) * thermal_gain,
+                );
+                let previous_output =
+                    reclip(driven, previous_thresholds, knee, reclip_passes);
+                let previous_asymmetric = asymmetric_clip(driven, previous_threshold, knee);
+                output += (previous_output - output) * previous_weight;
+                asymmetric += (previous_asymmetric - asymmetric) * previous_weight;
+            }
+            // Only the asymmetric part is DC filtered, so the regular clipper's output stays
+            // exactly the same with the control at zero
+            let asymmetric =
+                self.dc_blockers[channel_idx].process(asymmetric, double_precision);
+            output += (asymmetric - output) * even_harmonics;
+            self.clip_frame[channel_idx] = ClippedSample {
+                clean: clean_out,
+                clip_input,
+                passthrough,
+                driven,
+                symmetric_gain,
+                output,
+            };
+        }
+
+        if self.params.stereo_link.value() {
+            link_clip_gains(&mut self.clip_frame);
+        }
+
+        for (channel_idx, sample) in channel_samples.iter_mut().enumerate() {
+            let ClippedSample {
+                clean: clean_out,
+                clip_input,
+                passthrough,
+                driven,
+                mut output,
+                ..
+            } = self.clip_frame[channel_idx];
+            output = self.slew_limiters[channel_idx].process(
+                output,
+                slew_rate,
+                self.sample_rate,
+                slew_limit,
+            );
+            let sibilance_clipper = &mut self.sibilance_clippers[channel_idx];
+            sibilance_clipper.set_frequency(self.sample_rate, sibilance_frequency);
+            output = sibilance_clipper.process(
+                output,
+                clip_threshold,
+                knee,
+                sibilance_sensitivity,
+                double_precision,
+            );
+            if driven.abs() > f32::EPSILON {
+                clipper_gain = clipper_gain.min((output / driven).abs());
+            }
+            // This doesn't depend on the threshold, so it doesn't count as gain reduction
+            output = self.grunge_floors[channel_idx].process(output, grunge_floor);
+            distortion_frame.0 += driven * driven;
+            distortion_frame.1 += (output - driven) * (output - driven);
+            let (_, de_emphasis) = &mut self.emphasis_filters[channel_idx];
+            output = de_emphasis.process(output, double_precision);
+            // The solo replaces the wet signal and the mix is ignored. Everything after the clipper
+            // keeps running, so the filters and followers are up to date when the solo is turned
+            // off again.
+            let solo = match band_solo {
+                BandSolo::Off => None,
+                BandSolo::PreDistortion => Some(clip_input),
+                BandSolo::PostDistortion => Some(output),
+            };
+            output += passthrough;
+            compensation_frame.0 += clean_out * clean_out;
+            compensation_frame.1 += output * output;
+            compensation_frame.2 += clean_out * output;
+            output *= compensation_gain;
+            // The detectors keep running while disabled so they're up to date when enabled
+            let transient_gate = self.transient_detectors[channel_idx].process(
+                self.transient_lookahead_frame[channel_idx],
+                transient_sensitivity,
+            );
+            // The transient stage lets the unclipped signal through, which goes through the same
+            // stages as the wet signal up to that point. Otherwise moving it later in the chain
+            // would undo the EQ and the compressor everywhere but on the transients.
+            let mut unclipped = clean_out;
+            for stage in wet_chain {
+                match stage {
+                    WetStage::Transient => {
+                        if transient_clip {
+                            output = unclipped + (output - unclipped) * transient_gate;
+                        }
+                    }
+                    WetStage::Eq => {
+                        let wet_eq = &mut self.wet_eq[channel_idx];
+                        wet_eq.set_cutoffs(self.sample_rate, wet_cutoffs.0, wet_cutoffs.1);
+                        output = wet_eq.process(output, double_precision);
+                        let reference_eq = &mut self.transient_reference_eq[channel_idx];
+                        reference_eq.set_cutoffs(self.sample_rate, wet_cutoffs.0, wet_cutoffs.1);
+                        unclipped = reference_eq.process(unclipped, double_precision);
+                    }
+                    WetStage::Compressor => {
+                        // Like the input compressor, this keeps following the signal while
+                        // it's disabled
+                        let wet_compressor_state = &mut self.wet_compressors[channel_idx];
+                        let (attack_ms, release_ms) = wet_compressor_times;
+                        wet_compressor_state.set_times(self.sample_rate, attack_ms, release_ms);
+                        let wet_compressor_gain = wet_compressor_state.process(
+                            output,
+                            wet_compressor_threshold,
+                            wet_compressor_ratio,
+                        );
+                        if wet_compressor {
+                            output *= wet_compressor_gain * wet_compressor_makeup;
+                            unclipped *= wet_compressor_gain * wet_compressor_makeup;
+                        }
+                    }
+                }
+            }
+            let dry_eq = &mut self.dry_eq[channel_idx];
+            dry_eq.set_low_cut_slope(self.sample_rate, dry_low_cut_slope);
+            dry_eq.set_cutoffs(self.sample_rate, dry_cutoffs.0, dry_cutoffs.1);
+            let dry = dry_eq.process(clean_out, double_precision);
+
+            // The wet signal is stored in the buffer until it's been crossfed
+            if let Some(solo) = solo {
+                *sample = solo;
+                self.dry_frame[channel_idx] = (clean_out, 0.0);
+            } else {
+                *sample = limit_internal(output * drift_gain, internal_ceiling);
+                self.dry_frame[channel_idx] = (clean_out, dry);
+            }
+        }
+
+        self.thermal.add_frame(driven_peak, clip_threshold);
+        self.ceiling_follower.add_frame(driven_peak);
+        let (clean_power, wet_power, correlation) = compensation_frame;
+        self.drive_compensation
+            .add_frame(clean_power, wet_power, correlation);
+
+        let (dry_gain, wet_gain, gate_gain) = if band_solo == BandSolo::Off {
+            (dry_gain, wet_gain, gate_gain)
+        } else {
+            (0.0, 1.0, 1.0)
+        };
+
+        if crossfeed > 0.0 {
+            if let Some((left, right)) = stereo_frame(channel_samples) {
+                let amount = crossfeed * 0.5;
+                set_stereo_frame(
+                    channel_samples,
+                    (
+                        left + (right - left) * amount,
+                        right + (left - right) * amount,
+                    ),
+                );
+            }
+        }
+
+        for (channel_idx, sample) in channel_samples.iter_mut().enumerate() {
+            let (clean_out, dry) = self.dry_frame[channel_idx];
+            let output = *sample * gate_gain;
+            self.aux_frame[channel_idx] = output * output_gain;
+
+            // Wet/dry basically
+            // Combine distorted signal with original based on mix
+            *sample = (dry_gain * dry) + (wet_gain * output);
+
+            // The noise only shows up while there's signal coming in
+            let envelope = self.envelopes[channel_idx].process(clean_out);
+            if noise_gain > 0.0 {
+                let gate = (envelope / NOISE_GATE_LEVEL).min(1.0);
+                let noise = self.noise_generators[channel_idx].next() + hum;
+                *sample += noise * noise_gain * gate;
+            }
+
+            // The level match compares the signal before the trim is applied, so the result
+            // can simply replace the current trim
+            input_power += clean_out * clean_out;
+            output_power += *sample * *sample;
+            *sample *= output_gain;
+        }
+
+        self.wow_flutter.process(
+            channel_samples.iter_mut(),
+            self.sample_rate,
+            wow,
+            flutter,
+            self.params.wow_interpolation.value(),
+        );
+        self.aux_wow_flutter.process(
+            self.aux_frame.iter_mut(),
+            self.sample_rate,
+            wow,
+            flutter,
+            self.params.wow_interpolation.value(),
+        );
+
+        if self.level_measurement.is_active() {
+            self.level_measurement.add_frame(input_power, output_power, &self.level_match);
+        }
+
+        if self.limiter_enabled {
+            self.limiter.process(channel_samples);
+        }
+        self.aux_delay.process(self.aux_frame.iter_mut());
+
+        ProcessedFrame {
+            input_frame,
+            clipper_gain,
+            distortion_frame,
+            driven_peak,
+        }
+    }
+
+    /// The latency added by the limiter, which is zero while it's disabled.
+    fn limiter_latency(&self) -> usize {
+        if self.limiter_enabled {
+            self.limiter.lookahead()
+        } else {
+            0
+        }
+    }
+
+    /// The total latency introduced by the processing chain.
+    fn latency_samples(&self) -> u32 {
+        (self.limiter_latency() + self.transient_lookahead.delay()) as u32
+    }
+}
+
+/// A cutoff frequency parameter for the wet and dry path EQs.
+fn eq_cutoff_param(name: &str, default: f32) -> FloatParam {
+    FloatParam::new(
+        name,
+        default,
+        FloatRange::Skewed {
+            min: EQ_MIN_HZ,
+            max: EQ_MAX_HZ,
+            factor: FloatRange::skew_factor(-2.0),
+        },
+    )
+    .with_smoother(SmoothingStyle::Logarithmic(50.0))
+    .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+    .with_string_to_value(formatters::s2v_f32_hz_then_khz())
+}
+
+/// The clip threshold for the current mode. In brickwall mode the ceiling sits at 0 dBFS and the
+/// drive does all the work.
+fn mode_threshold(brickwall: bool, threshold: f32) -> f32 {
+    if brickwall {
+        1.0
+    } else {
+        threshold
+    }
+}
+
+/// Clip the signal with a soft knee below the threshold. `knee` is the fraction of the threshold
+/// over which the signal is gradually compressed into the ceiling, so a knee of zero is a regular
+/// hard clip.
+fn knee_clip(input: f32, threshold: f32, knee: f32) -> f32 {
+    let knee_start = threshold * (1.0 - knee);
+    let knee_range = threshold - knee_start;
+    if knee_range <= 0.0 {
+        return hard_clip(input, threshold);
+    }
+
+    // The curve is linear up to the knee and then bends into the ceiling with a matching slope
+    let magnitude = input.abs();
+    if magnitude <= knee_start {
+        input
+    } else {
+        let compressed = knee_start + knee_range * ((magnitude - knee_start) / knee_range).tanh();
+        compressed.copysign(input)
+    }
+}
+
+/// Run the signal through [`split_clip()`] `passes` times, boosting it by [`RECLIP_GAIN`] before
+/// every pass after the first one. `thresholds` are the positive and negative thresholds.
+fn reclip(input: f32, thresholds: (f32, f32), knee: f32, passes: i32) -> f32 {
+    (1..passes).fold(split_clip(input, thresholds, knee), |output, _| {
+        split_clip(output * RECLIP_GAIN, thresholds, knee)
+    })
+}
+
+/// [`knee_clip()`] with separate thresholds for the positive and negative halves of the waveform.
+/// With equal thresholds this is the same as the regular clipper.
+fn split_clip(input: f32, (positive_threshold, negative_threshold): (f32, f32), knee: f32) -> f32 {
+    if input >= 0.0 {
+        knee_clip(input, positive_threshold, knee)
+    } else {
+        knee_clip(input, negative_threshold, knee)
+    }
+}
+
+/// Keep an intermediate value within `ceiling`. NaNs are silenced, since there's no way to tell
+/// what they should have been.
+fn limit_internal(sample: f32, ceiling: f32) -> f32 {
+    if sample.is_nan() {
+        0.0
+    } else {
+        sample.clamp(-ceiling, ceiling)
+    }
+}
+
+/// Crossover distortion. Samples within `deadband` of zero are silenced, and everything else is
+/// moved towards zero by the same amount so the waveform stays continuous. A deadband of zero
+/// leaves the signal unchanged.
+fn crossover(input: f32, deadband: f32) -> f32 {
+    (input.abs() - deadband).max(0.0).copysign(input)
+}
+
+fn hard_clip(input: f32, threshold: f32) -> f32 {
+    let mut output = input;
+    //Split these up for positive and negative input values?????
+
+    if output > threshold {
+        output = threshold;
+        // input = threshold + (1.0/(input-threshold));
+    } else if output < -threshold {
+        output = -threshold;
+        // input = -threshold - (1.0/(input-threshold));
+    }
+
+    output
+}
+
+/// The parameter's value for the current sample. The smoother is always advanced so it doesn't jump
+/// when switching back from instant to smoothed values.
+fn next_value(param: &FloatParam, instant: bool) -> f32 {
+    let smoothed = param.smoothed.next();
+    if instant {
+        param.value()
+    } else {
+        smoothed
+    }
+}
+
+/// The highest absolute sample value in the buffer.
+fn buffer_peak(buffer: &Buffer) -> f32 {
+    buffer
+        .as_slice_immutable()
+        .iter()
+        .flat_map(|channel| channel.iter())
+        .fold(0.0f32, |peak, sample| peak.max(sample.abs()))
+}
+
+/// Replace every NaN or infinite sample in the buffer with silence.
+fn sanitize_buffer(buffer: &mut Buffer) {
+    for sample in buffer.as_slice().iter_mut().flat_map(|channel| channel.iter_mut()) {
+        *sample = finite_or_zero(*sample);
+    }
+}
+
+fn finite_or_zero(sample: f32) -> f32 {
+    if sample.is_finite() {
+        sample
+    } else {
+        0.0
+    }
+}
+
+/// Give every channel the symmetric clipper gain of the channel that's clipped the hardest. The
+/// extra gain reduction is applied on top of each channel's shaped output, so the asymmetric and
+/// DC shaping is kept and the output doesn't drop out when a channel crosses zero.
+fn link_clip_gains(clip_frame: &mut [ClippedSample]) {
+    let linked_gain = clip_frame
+        .iter()
+        .fold(1.0f32, |gain, clipped| gain.min(clipped.symmetric_gain));
+
+    for clipped in clip_frame {
+        if clipped.symmetric_gain > f32::EPSILON {
+            clipped.output *= linked_gain / clipped.symmetric_gain;
+        }
+    }
+}
+
+/// Overwrite the left and right samples of a stereo frame. Does nothing for other channel counts.
+fn set_stereo_frame(channel_samples: &mut ChannelSamples, (left, right): (f32, f32)) {
+    if channel_samples.len() != 2 {
+        return;
+    }
+
+    if let Some(sample) = channel_samples.get_mut(0) {
+        *sample = left;
+    }
+    if let Some(sample) = channel_samples.get_mut(1) {
+        *sample = right;
+    }
+}
+
+/// The left and right samples of a frame, if the buffer is stereo.
+fn stereo_frame(channel_samples: &mut ChannelSamples) -> Option<(f32, f32)> {
+    if channel_samples.len() != 2 {
+        return None;
+    }
+
+    let left = *channel_samples.get_mut(0)?;
+    let right = *channel_samples.get_mut(1)?;
+
+    Some((left, right))
+}
+
 impl ClapPlugin for Distortion {
     const CLAP_ID: &'static str = "com.your-domain.distortion";
     const CLAP_DESCRIPTION: Option<&'static str> = Some("Crunchy distortion plugin");
@@ -221,6 +2513,11 @@ impl ClapPlugin for Distortion {
 
     // Don't forget to change these features
     const CLAP_FEATURES: &'static [ClapFeature] = &[ClapFeature::AudioEffect, ClapFeature::Stereo];
+
+    const CLAP_POLY_MODULATION_CONFIG: Option<PolyModulationConfig> = Some(PolyModulationConfig {
+        max_voice_capacity: MAX_VOICES,
+        supports_overlapping_voices: true,
+    });
 }
 
 impl Vst3Plugin for Distortion {
@@ -233,3 +2530,396 @@ impl Vst3Plugin for Distortion {
 
 nih_export_clap!(Distortion);
 nih_export_vst3!(Distortion);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    /// The Pearson correlation between two signals.
+    fn correlation(a: &[f32], b: &[f32]) -> f32 {
+        let mean_a = a.iter().sum::<f32>() / a.len() as f32;
+        let mean_b = b.iter().sum::<f32>() / b.len() as f32;
+        let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+        for (a, b) in a.iter().zip(b) {
+            covariance += (a - mean_a) * (b - mean_b);
+            variance_a += (a - mean_a) * (a - mean_a);
+            variance_b += (b - mean_b) * (b - mean_b);
+        }
+
+        covariance / (variance_a * variance_b).sqrt()
+    }
+
+    /// Clip a loud left channel and a quiet right channel that carry the same sine, optionally
+    /// linking their gains, and return both outputs.
+    fn clip_stereo_sine(linked: bool) -> (Vec<f32>, Vec<f32>) {
+        let (mut left, mut right) = (Vec::new(), Vec::new());
+        for i in 0..1000 {
+            let sine = (TAU * i as f32 / 100.0).sin();
+            let mut clip_frame = [4.0 * sine, sine].map(|driven| {
+                let output = reclip(driven, (1.0, 1.0), 0.5, 1);
+                ClippedSample {
+                    driven,
+                    symmetric_gain: if driven.abs() > f32::EPSILON {
+                        (output / driven).abs().min(1.0)
+                    } else {
+                        1.0
+                    },
+                    output,
+                    ..ClippedSample::default()
+                }
+            });
+            if linked {
+                link_clip_gains(&mut clip_frame);
+            }
+            left.push(clip_frame[0].output);
+            right.push(clip_frame[1].output);
+        }
+
+        (left, right)
+    }
+
+    #[test]
+    fn linked_clip_gains_keep_channels_correlated() {
+        let (left, right) = clip_stereo_sine(false);
+        let unlinked = correlation(&left, &right);
+        let (left, right) = clip_stereo_sine(true);
+        let linked = correlation(&left, &right);
+
+        assert!(linked > 0.999, "linked correlation {linked}");
+        assert!(linked > unlinked, "linked {linked}, unlinked {unlinked}");
+    }
+
+    #[test]
+    fn linked_clip_gains_keep_shaped_output_at_zero_crossings() {
+        // A channel at a zero crossing must not mute the others or lose its own shaping
+        let mut clip_frame = [
+            ClippedSample {
+                driven: 0.0,
+                symmetric_gain: 1.0,
+                output: 0.01,
+                ..ClippedSample::default()
+            },
+            ClippedSample {
+                driven: 2.0,
+                symmetric_gain: 0.5,
+                output: 1.1,
+                ..ClippedSample::default()
+            },
+        ];
+        link_clip_gains(&mut clip_frame);
+
+        assert!((clip_frame[0].output - 0.005).abs() < 1e-6);
+        assert!((clip_frame[1].output - 1.1).abs() < 1e-6);
+    }
+
+    /// The ratio between a signal's peak and its RMS level, which approaches 1 for a square wave.
+    fn crest_factor(signal: &[f32]) -> f32 {
+        let peak = signal
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        let rms =
+            (signal.iter().map(|sample| sample * sample).sum::<f32>() / signal.len() as f32).sqrt();
+
+        peak / rms
+    }
+
+    #[test]
+    fn more_reclip_passes_flatten_the_waveform() {
+        let crest_factors: Vec<f32> = [1, 2, 4, MAX_RECLIP_PASSES]
+            .iter()
+            .map(|&passes| {
+                let clipped: Vec<f32> = (0..1000)
+                    .map(|i| {
+                        let sine = 2.0 * (TAU * i as f32 / 100.0).sin();
+                        reclip(sine, (1.0, 1.0), 0.5, passes)
+                    })
+                    .collect();
+                crest_factor(&clipped)
+            })
+            .collect();
+
+        for pair in crest_factors.windows(2) {
+            assert!(pair[1] < pair[0], "crest factors {crest_factors:?}");
+        }
+    }
+
+    const PATHOLOGICAL_SAMPLES: [f32; 7] = [
+        f32::NAN,
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+        f32::MAX,
+        -1e30,
+        1e20,
+        f32::MIN_POSITIVE,
+    ];
+
+    #[test]
+    fn internal_ceiling_keeps_huge_spikes_finite() {
+        let ceiling = util::db_to_gain(96.0);
+        for input in PATHOLOGICAL_SAMPLES {
+            // Extreme drive on top of the spike, like the clipper sees it
+            let limited = limit_internal(input * 1e6, ceiling);
+            assert!(
+                limited.is_finite() && limited.abs() <= ceiling,
+                "{input} -> {limited}"
+            );
+
+            let clipped = reclip(limited, (0.5, 0.25), 1.0, MAX_RECLIP_PASSES);
+            let asymmetric = asymmetric_clip(limited, 0.5, 1.0);
+            assert!(clipped.is_finite() && asymmetric.is_finite(), "{input}");
+        }
+    }
+
+    #[test]
+    fn sanitize_buffer_silences_non_finite_samples() {
+        let mut left = PATHOLOGICAL_SAMPLES.to_vec();
+        let mut right = vec![0.5; PATHOLOGICAL_SAMPLES.len()];
+        right[1] = f32::NAN;
+        let num_samples = left.len();
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(num_samples, |output_slices| {
+                *output_slices = vec![&mut left, &mut right];
+            })
+        };
+
+        sanitize_buffer(&mut buffer);
+        for channel in buffer.as_slice_immutable() {
+            assert!(
+                channel.iter().all(|sample| sample.is_finite()),
+                "{channel:?}"
+            );
+        }
+        assert_eq!(left[..3], [0.0; 3]);
+        assert_eq!(left[3], f32::MAX);
+        assert_eq!(right[..3], [0.5, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn split_clip_with_equal_thresholds_is_symmetric() {
+        for knee in [0.0, 0.5, 1.0] {
+            for i in -300..=300 {
+                let input = i as f32 / 100.0;
+                let split = split_clip(input, (0.7, 0.7), knee);
+                assert_eq!(
+                    split,
+                    knee_clip(input, 0.7, knee),
+                    "{input} with knee {knee}"
+                );
+                assert_eq!(split, -split_clip(-input, (0.7, 0.7), knee));
+            }
+        }
+    }
+
+    /// A sine with the given amplitude that repeats every `period` samples.
+    fn sine(amplitude: f32, period: usize, i: usize) -> f32 {
+        amplitude * (TAU * i as f32 / period as f32).sin()
+    }
+
+    /// The magnitude of a harmonic of a signal that repeats every `period` samples, using a DFT at
+    /// just that bin.
+    fn harmonic_magnitude(signal: &[f32], period: usize, harmonic: usize) -> f32 {
+        let (re, im) = signal
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(re, im), (i, sample)| {
+                let phase = TAU * (harmonic * i) as f32 / period as f32;
+                (re + sample * phase.cos(), im - sample * phase.sin())
+            });
+
+        (re * re + im * im).sqrt() / signal.len() as f32
+    }
+
+    #[test]
+    fn split_thresholds_add_even_harmonics() {
+        let period = 64;
+        let clip = |thresholds| -> Vec<f32> {
+            (0..period * 16)
+                .map(|i| split_clip(sine(2.0, period, i), thresholds, 0.5))
+                .collect()
+        };
+
+        let symmetric = clip((1.0, 1.0));
+        let split = clip((1.0, 0.5));
+        assert!(harmonic_magnitude(&symmetric, period, 2) < 1e-4);
+        assert!(harmonic_magnitude(&split, period, 2) > 0.01);
+    }
+
+    #[test]
+    fn even_harmonics_shift_the_even_odd_balance() {
+        let period = 64;
+        let ratios: Vec<f32> = [0.0, 0.25, 0.5, 1.0]
+            .iter()
+            .map(|&even_harmonics| {
+                // The same blend as in `process()`, the DC doesn't matter for the harmonics
+                let output: Vec<f32> = (0..period * 16)
+                    .map(|i| {
+                        let driven = sine(2.0, period, i);
+                        let symmetric = reclip(driven, (1.0, 1.0), 0.5, 1);
+                        let asymmetric = asymmetric_clip(driven, 1.0, 0.5);
+                        symmetric + (asymmetric - symmetric) * even_harmonics
+                    })
+                    .collect();
+                harmonic_magnitude(&output, period, 2) / harmonic_magnitude(&output, period, 3)
+            })
+            .collect();
+
+        assert!(ratios[0] < 1e-3, "ratios {ratios:?}");
+        for pair in ratios.windows(2) {
+            assert!(pair[1] > pair[0], "ratios {ratios:?}");
+        }
+    }
+
+    #[test]
+    fn knee_clip_at_different_knees() {
+        // Without a knee this is a flat clamp
+        assert_eq!(knee_clip(0.9, 1.0, 0.0), 0.9);
+        assert_eq!(knee_clip(1.5, 1.0, 0.0), 1.0);
+        assert_eq!(knee_clip(-1.5, 1.0, 0.0), -1.0);
+
+        // Half a knee leaves the signal alone up to half the threshold and then bends into it
+        assert_eq!(knee_clip(0.5, 1.0, 0.5), 0.5);
+        let bent = knee_clip(0.9, 1.0, 0.5);
+        assert!(bent > 0.5 && bent < 0.9, "{bent}");
+
+        // The full knee starts reducing the gain right away
+        let soft = knee_clip(0.5, 1.0, 1.0);
+        assert!(soft < 0.5, "{soft}");
+
+        // More knee means more gain reduction below the ceiling, and nothing ever goes past it
+        for i in 1..=400 {
+            let input = i as f32 / 100.0;
+            let outputs = [0.0, 0.5, 1.0].map(|knee| knee_clip(input, 1.0, knee));
+            assert!(
+                outputs[0] >= outputs[1] && outputs[1] >= outputs[2],
+                "{outputs:?}"
+            );
+            assert!(outputs.iter().all(|output| *output <= 1.0), "{outputs:?}");
+        }
+    }
+
+    /// Ignores everything the plugin asks for during `initialize()`.
+    struct TestInitContext;
+
+    impl InitContext<Distortion> for TestInitContext {
+        fn plugin_api(&self) -> PluginApi {
+            PluginApi::Clap
+        }
+
+        fn execute(&self, _task: Task) {}
+
+        fn set_latency_samples(&self, _samples: u32) {}
+
+        fn set_current_voice_capacity(&self, _capacity: u32) {}
+    }
+
+    /// The stages that add latency, each of which can be enabled separately.
+    #[derive(Debug, Clone, Copy)]
+    struct LatencySettings {
+        limiter: bool,
+        transient_lookahead: bool,
+        mode_crossfade: bool,
+    }
+
+    /// Push an impulse through an initialized stereo plugin one frame at a time, the same way
+    /// `process()` does, and return the sample the main and aux outputs peak on. The smoothers only
+    /// get their values from the host, so this uses instant response to read the parameters
+    /// directly.
+    fn impulse_peaks(settings: LatencySettings, mix: f32) -> (u32, usize, usize) {
+        let params = DistortionParams {
+            instant: BoolParam::new("Instant Response", true),
+            mix: FloatParam::new("Mix", mix, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            limiter: BoolParam::new("Limiter", settings.limiter),
+            transient_lookahead: BoolParam::new(
+                "Transient Look-Ahead",
+                settings.transient_lookahead,
+            ),
+            ..DistortionParams::default()
+        };
+        let mut plugin = Distortion {
+            params: Arc::new(params),
+            ..Distortion::default()
+        };
+        let audio_io_layout = AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            ..AudioIOLayout::const_default()
+        };
+        let buffer_config = BufferConfig {
+            sample_rate: 48_000.0,
+            min_buffer_size: None,
+            max_buffer_size: 1024,
+            process_mode: ProcessMode::Realtime,
+        };
+        assert!(plugin.initialize(&audio_io_layout, &buffer_config, &mut TestInitContext));
+        plugin.reset();
+        if settings.mode_crossfade {
+            // This starts a crossfade back to the regular mode on the first sample
+            plugin.brickwall_crossfade.reset(true);
+        }
+
+        let mut left = vec![0.0; 1024];
+        left[0] = 0.5;
+        let mut right = left.clone();
+        let mut aux = vec![0.0; left.len()];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(left.len(), |output_slices| {
+                *output_slices = vec![&mut left, &mut right];
+            })
+        };
+        for (sample_idx, mut channel_samples) in buffer.iter_samples().enumerate() {
+            plugin.process_frame(&mut channel_samples);
+            aux[sample_idx] = plugin.aux_frame[0];
+        }
+        assert_eq!(left, right);
+
+        let peak = |signal: &[f32]| {
+            (0..signal.len())
+                .max_by(|a, b| signal[*a].abs().total_cmp(&signal[*b].abs()))
+                .unwrap()
+        };
+        (plugin.latency_samples(), peak(&left), peak(&aux))
+    }
+
+    #[test]
+    fn dry_and_wet_land_at_the_reported_latency() {
+        let off = LatencySettings {
+            limiter: false,
+            transient_lookahead: false,
+            mode_crossfade: false,
+        };
+        for settings in [
+            off,
+            LatencySettings {
+                limiter: true,
+                ..off
+            },
+            LatencySettings {
+                transient_lookahead: true,
+                ..off
+            },
+            LatencySettings {
+                mode_crossfade: true,
+                ..off
+            },
+            LatencySettings {
+                limiter: true,
+                transient_lookahead: true,
+                mode_crossfade: true,
+            },
+        ] {
+            let (latency, dry_peak, _) = impulse_peaks(settings, 0.0);
+            assert_eq!(
+                latency > 0,
+                settings.limiter || settings.transient_lookahead,
+                "{settings:?}"
+            );
+            assert_eq!(dry_peak, latency as usize, "dry with {settings:?}");
+            let (latency, wet_peak, aux_peak) = impulse_peaks(settings, 1.0);
+            assert_eq!(wet_peak, latency as usize, "wet with {settings:?}");
+            assert_eq!(aux_peak, latency as usize, "aux with {settings:?}");
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use nih_plug::prelude::ChannelSamples;
+
+use crate::envelope;
+
+/// The output ceiling enforced by the limiter, 0 dBFS.
+pub const LIMITER_CEILING: f32 = 1.0;
+/// The longest look-ahead the limiter supports. Buffers are sized for this in `new()`.
+pub const MAX_LOOKAHEAD_MS: f32 = 5.0;
+const RELEASE_MS: f32 = 80.0;
+
+/// A stereo-linked peak limiter with look-ahead. The gain reduction needed for each incoming frame
+/// is held for the look-ahead window and then faded in over that same window, so by the time the
+/// delayed peak reaches the output the gain is already low enough to keep it under the ceiling.
+#[derive(Debug, Default)]
+pub struct LookaheadLimiter {
+    /// One delay line per channel, each `max_window` samples long.
+    delay_lines: Vec<Vec<f32>>,
+    /// Gain values for the moving average used to smooth the attack.
+    gain_history: Vec<f32>,
+    gain_sum: f64,
+    /// A monotonic queue of `(frame_idx, gain)` pairs used to compute the minimum required gain
+    /// over the look-ahead window in constant time. This is a ring buffer with `max_window`
+    /// slots.
+    min_queue: Vec<(u64, f32)>,
+    min_queue_start: usize,
+    min_queue_len: usize,
+
+    max_window: usize,
+    /// The look-ahead in samples. The window is one sample longer than this.
+    lookahead: usize,
+    frame_idx: u64,
+    pos: usize,
+
+    release_coeff: f32,
+    release_gain: f32,
+}
+
+impl LookaheadLimiter {
+    /// Allocate the buffers needed for the maximum look-ahead time. This should be called from
+    /// `initialize()`.
+    pub fn new(num_channels: usize, sample_rate: f32) -> Self {
+        let max_window = (MAX_LOOKAHEAD_MS / 1000.0 * sample_rate).ceil() as usize + 1;
+
+        let mut limiter = Self {
+            delay_lines: vec![vec![0.0; max_window]; num_channels],
+            gain_history: vec![1.0; max_window],
+            min_queue: vec![(0, 1.0); max_window],
+            max_window,
+            release_coeff: envelope::time_constant(sample_rate, RELEASE_MS),
+            ..Self::default()
+        };
+        limiter.reset();
+
+        limiter
+    }
+
+    /// Change the look-ahead time in samples. This resets the limiter's state.
+    pub fn set_lookahead(&mut self, lookahead: usize) {
+        self.lookahead = lookahead.min(self.max_window.saturating_sub(1));
+        self.reset();
+    }
+
+    /// The current look-ahead in samples. This is also the latency added by the limiter.
+    pub fn lookahead(&self) -> usize {
+        self.lookahead
+    }
+
+    pub fn reset(&mut self) {
+        for delay_line in &mut self.delay_lines {
+            delay_line.fill(0.0);
+        }
+        self.gain_history.fill(1.0);
+        self.gain_sum = (self.lookahead + 1) as f64;
+        self.min_queue_start = 0;
+        self.min_queue_len = 0;
+
+        self.frame_idx = 0;
+        self.pos = 0;
+        self.release_gain = 1.0;
+    }
+
+    /// Limit a single frame in place. The output is delayed by the look-ahead time.
+    pub fn process(&mut self, channel_samples: &mut ChannelSamples) {
+        if self.max_window == 0 {
+            return;
+        }
+
+        let peak = channel_samples
+            .iter_mut()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        let required_gain = if peak > LIMITER_CEILING {
+            LIMITER_CEILING / peak
+        } else {
+            1.0
+        };
+
+        // The minimum gain over the window is applied instantly and released smoothly, and the
+        // moving average then turns the instant attack into a ramp that spans the look-ahead
+        let held_gain = self.push_min(required_gain);
+        self.release_gain = if held_gain < self.release_gain {
+            held_gain
+        } else {
+            held_gain + self.release_coeff * (self.release_gain - held_gain)
+        };
+
+        let window = self.lookahead + 1;
+        let oldest_gain_pos = (self.pos + self.max_window - window) % self.max_window;
+        self.gain_sum -= self.gain_history[oldest_gain_pos] as f64;
+        self.gain_history[self.pos] = self.release_gain;
+        self.gain_sum += self.release_gain as f64;
+        let gain = (self.gain_sum / window as f64) as f32;
+
+        let read_pos = (self.pos + self.max_window - self.lookahead) % self.max_window;
+        for (sample, delay_line) in channel_samples.iter_mut().zip(&mut self.delay_lines) {
+            delay_line[self.pos] = *sample;
+            // The average can drift by a rounding error, so the ceiling is still clamped hard
+            *sample = (delay_line[read_pos] * gain).clamp(-LIMITER_CEILING, LIMITER_CEILING);
+        }
+
+        self.pos = (self.pos + 1) % self.max_window;
+        self.frame_idx += 1;
+    }
+
+    /// Add a gain value to the sliding minimum and return the minimum over the current window.
+    fn push_min(&mut self, gain: f32) -> f32 {
+        // Values that have left the window are dropped first so the ring buffer never overflows
+        let window = (self.lookahead + 1) as u64;
+        while self.min_queue_len > 0
+            && self.min_queue[self.min_queue_start].0 + window <= self.frame_idx
+        {
+            self.min_queue_start = (self.min_queue_start + 1) % self.max_window;
+            self.min_queue_len -= 1;
+        }
+
+        while self.min_queue_len > 0 {
+            let back = (self.min_queue_start + self.min_queue_len - 1) % self.max_window;
+            if self.min_queue[back].1 >= gain {
+                self.min_queue_len -= 1;
+            } else {
+                break;
+            }
+        }
+
+        let back = (self.min_queue_start + self.min_queue_len) % self.max_window;
+        self.min_queue[back] = (self.frame_idx, gain);
+        self.min_queue_len += 1;
+
+        self.min_queue[self.min_queue_start].1
+    }
+}
@@ -0,0 +1,277 @@
+use atomic_float::AtomicF32;
+use nih_plug::params::persist::PersistentField;
+use nih_plug::prelude::{util, Enum};
+use std::sync::atomic::Ordering;
+
+/// The release times the editor lets the user cycle through for the digital peak meter.
+pub const PEAK_METER_DECAY_STEPS_MS: [f32; 6] = [50.0, 100.0, 150.0, 300.0, 600.0, 1200.0];
+/// The peak meter's default release time in digital mode.
+const DEFAULT_PEAK_METER_DECAY_MS: f32 = 150.0;
+/// The rates the editor lets the user cycle through for redrawing the meters. Lower rates use less
+/// CPU on slower machines. The first one is the default.
+pub const METER_REFRESH_RATES_HZ: [u32; 3] = [60, 30, 15];
+/// How long the held peak takes to fall by 20 dB.
+const PEAK_HOLD_FALL_MS: f64 = 6000.0;
+/// The averaging time for the residual DC readout. This needs to be long enough that low bass
+/// notes average out to zero.
+const DC_OFFSET_AVERAGE_MS: f64 = 1000.0;
+/// The averaging time for the distortion amount readout.
+const DISTORTION_AVERAGE_MS: f64 = 300.0;
+
+/// How the output meter responds to level changes.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeterBallistics {
+    /// Instant attack with a fast release.
+    Digital,
+    /// A quasi-peak programme meter with a 10 ms integration time and a slow fall back.
+    #[name = "PPM"]
+    Ppm,
+    /// A slow averaging meter that takes 300 ms to rise and fall.
+    #[name = "VU"]
+    Vu,
+}
+
+/// Which signal the main meter shows.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeterSource {
+    /// The plugin's output.
+    Output,
+    /// The driven signal going into the clipper, which shows how far it's pushed past the
+    /// threshold.
+    #[name = "Pre-Clip"]
+    PreClip,
+    /// The clipper's output before it's mixed with the dry signal.
+    #[name = "Post-Clip"]
+    PostClip,
+}
+
+impl MeterSource {
+    /// Pick this source's sample out of the three taps.
+    pub fn select(self, pre_clip: f32, post_clip: f32, output: f32) -> f32 {
+        match self {
+            MeterSource::Output => output,
+            MeterSource::PreClip => pre_clip,
+            MeterSource::PostClip => post_clip,
+        }
+    }
+}
+
+/// The output levels shown by the editor's meters, as linear amplitudes.
+#[derive(Debug)]
+pub struct PeakMeters {
+    /// A single meter for all channels combined.
+    pub summed: AtomicF32,
+    /// Separate meters for the left and right channels.
+    pub channels: [AtomicF32; 2],
+    /// The highest recent sample peak in any channel. This falls back down slowly, independent of
+    /// the meter's ballistics, and the editor can reset it.
+    pub held: AtomicF32,
+    /// The residual DC offset in the left and right channels' output, as a slow signed average.
+    pub dc_offsets: [AtomicF32; 2],
+    /// How much the clipper currently changes the signal, from 0 for an untouched signal to 1
+    /// when the changes are as loud as the signal itself. This is independent of the level.
+    pub distortion: AtomicF32,
+    /// The highest recent peak of the driven signal going into the clipper, for the editor's gain
+    /// staging guide. This falls back down like the held peak.
+    pub driven: AtomicF32,
+}
+
+impl Default for PeakMeters {
+    fn default() -> Self {
+        Self {
+            summed: AtomicF32::new(util::MINUS_INFINITY_DB),
+            channels: std::array::from_fn(|_| AtomicF32::new(util::MINUS_INFINITY_DB)),
+            held: AtomicF32::new(0.0),
+            dc_offsets: std::array::from_fn(|_| AtomicF32::new(0.0)),
+            distortion: AtomicF32::new(0.0),
+            driven: AtomicF32::new(0.0),
+        }
+    }
+}
+
+impl PeakMeters {
+    /// Update the held peak with a new sample peak. `fall_weight` comes from
+    /// [`peak_hold_fall_weight()`].
+    pub fn hold(&self, peak: f32, fall_weight: f32) {
+        let held = self.held.load(Ordering::Relaxed) * fall_weight;
+        self.held.store(held.max(peak), Ordering::Relaxed);
+    }
+
+    /// Update the driven signal's held peak, like [`hold()`][Self::hold()].
+    pub fn hold_driven(&self, peak: f32, fall_weight: f32) {
+        let held = self.driven.load(Ordering::Relaxed) * fall_weight;
+        self.driven.store(held.max(peak), Ordering::Relaxed);
+    }
+
+    pub fn reset_held(&self) {
+        self.held.store(0.0, Ordering::Relaxed);
+    }
+
+    /// Add a channel's output sample to its residual DC average. `weight` comes from
+    /// [`dc_offset_weight()`].
+    pub fn track_dc(&self, channel_idx: usize, sample: f32, weight: f32) {
+        if let Some(dc_offset) = self.dc_offsets.get(channel_idx) {
+            let average = dc_offset.load(Ordering::Relaxed) * weight + sample * (1.0 - weight);
+            dc_offset.store(average, Ordering::Relaxed);
+        }
+    }
+
+    /// Add a frame's distortion amount to the average. `weight` comes from
+    /// [`distortion_weight()`].
+    pub fn track_distortion(&self, amount: f32, weight: f32) {
+        let average = self.distortion.load(Ordering::Relaxed) * weight + amount * (1.0 - weight);
+        self.distortion.store(average, Ordering::Relaxed);
+    }
+}
+
+/// The next lower meter refresh rate, wrapping around to the highest one.
+pub fn next_refresh_rate_hz(refresh_hz: u32) -> u32 {
+    METER_REFRESH_RATES_HZ
+        .iter()
+        .copied()
+        .find(|&rate_hz| rate_hz < refresh_hz)
+        .unwrap_or(METER_REFRESH_RATES_HZ[0])
+}
+
+/// The per-sample weight for the held peak's slow fall.
+pub fn peak_hold_fall_weight(sample_rate: f32) -> f32 {
+    decay_weight(sample_rate, PEAK_HOLD_FALL_MS, 0.1)
+}
+
+/// The per-sample weight for the residual DC average.
+pub fn dc_offset_weight(sample_rate: f32) -> f32 {
+    decay_weight(sample_rate, DC_OFFSET_AVERAGE_MS, 0.01)
+}
+
+/// The per-sample weight for the distortion amount's average.
+pub fn distortion_weight(sample_rate: f32) -> f32 {
+    decay_weight(sample_rate, DISTORTION_AVERAGE_MS, 0.01)
+}
+
+/// The per-sample weights for the meter's one pole smoothing. These are the fraction of the
+/// previous meter value that is kept when the level rises or falls.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeterCoefficients {
+    attack: f32,
+    release: f32,
+}
+
+impl Default for MeterCoefficients {
+    fn default() -> Self {
+        Self {
+            attack: 0.0,
+            release: 1.0,
+        }
+    }
+}
+
+impl MeterCoefficients {
+    pub fn new(ballistics: MeterBallistics, sample_rate: f32, decay: &MeterDecay) -> Self {
+        match ballistics {
+            MeterBallistics::Digital => Self {
+                attack: 0.0,
+                release: decay.release_weight(),
+            },
+            // Reaches -2 dB of a step within 10 ms and falls 20 dB in 2.8 seconds
+            MeterBallistics::Ppm => Self {
+                attack: decay_weight(sample_rate, 10.0, 0.2),
+                release: decay_weight(sample_rate, 2800.0, 0.1),
+            },
+            // Reaches 99% of a step within 300 ms in both directions
+            MeterBallistics::Vu => Self {
+                attack: decay_weight(sample_rate, 300.0, 0.01),
+                release: decay_weight(sample_rate, 300.0, 0.01),
+            },
+        }
+    }
+
+    /// Move the meter's current value towards a new amplitude.
+    pub fn apply(&self, current: f32, amplitude: f32) -> f32 {
+        let weight = if amplitude > current {
+            self.attack
+        } else {
+            self.release
+        };
+
+        current * weight + amplitude * (1.0 - weight)
+    }
+}
+
+/// The digital peak meter's user-adjustable release time. This is an editor setting rather than a
+/// parameter, so it's persisted with the plugin's state. The release weight is recomputed
+/// whenever the time or the sample rate changes, which only happens from the GUI or during
+/// initialization, so the audio thread only has to load it.
+#[derive(Debug)]
+pub struct MeterDecay {
+    decay_ms: AtomicF32,
+    sample_rate: AtomicF32,
+    release_weight: AtomicF32,
+}
+
+impl Default for MeterDecay {
+    fn default() -> Self {
+        Self {
+            decay_ms: AtomicF32::new(DEFAULT_PEAK_METER_DECAY_MS),
+            sample_rate: AtomicF32::new(0.0),
+            release_weight: AtomicF32::new(1.0),
+        }
+    }
+}
+
+impl<'a> PersistentField<'a, f32> for MeterDecay {
+    fn set(&self, new_value: f32) {
+        self.set_decay_ms(new_value);
+    }
+
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&f32) -> R,
+    {
+        f(&self.decay_ms())
+    }
+}
+
+impl MeterDecay {
+    pub fn decay_ms(&self) -> f32 {
+        self.decay_ms.load(Ordering::Relaxed)
+    }
+
+    /// The next longer release time, wrapping around to the shortest one.
+    pub fn next_step_ms(&self) -> f32 {
+        let decay_ms = self.decay_ms();
+        PEAK_METER_DECAY_STEPS_MS
+            .iter()
+            .copied()
+            .find(|&step_ms| step_ms > decay_ms)
+            .unwrap_or(PEAK_METER_DECAY_STEPS_MS[0])
+    }
+
+    pub fn set_decay_ms(&self, decay_ms: f32) {
+        self.decay_ms.store(decay_ms, Ordering::Relaxed);
+        self.update_release_weight();
+    }
+
+    /// Called from `initialize()`, since the weight depends on the sample rate.
+    pub fn set_sample_rate(&self, sample_rate: f32) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+        self.update_release_weight();
+    }
+
+    pub fn release_weight(&self) -> f32 {
+        self.release_weight.load(Ordering::Relaxed)
+    }
+
+    fn update_release_weight(&self) {
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed);
+        if sample_rate > 0.0 {
+            let weight = decay_weight(sample_rate, self.decay_ms() as f64, 0.25);
+            self.release_weight.store(weight, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The per-sample weight for a one pole filter that leaves `remaining` of the previous value after
+/// `time_ms` milliseconds.
+fn decay_weight(sample_rate: f32, time_ms: f64, remaining: f64) -> f32 {
+    remaining.powf((sample_rate as f64 * time_ms / 1000.0).recip()) as f32
+}
@@ -0,0 +1,47 @@
+use nih_plug::prelude::Enum;
+use std::f32::consts::FRAC_PI_2;
+
+/// How the dry and wet signals are crossfaded by the mix parameter.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MixLaw {
+    /// A straight `(1 - mix)` and `mix` crossfade. Uncorrelated signals dip by 3 dB at 50%.
+    Linear,
+    /// A sine/cosine crossfade that keeps the combined power constant for uncorrelated signals.
+    #[name = "Equal Power"]
+    EqualPower,
+}
+
+impl MixLaw {
+    /// The `(dry, wet)` gains for a mix amount between 0 and 1.
+    pub fn gains(self, mix: f32) -> (f32, f32) {
+        match self {
+            MixLaw::Linear => (1.0 - mix, mix),
+            MixLaw::EqualPower => {
+                let (wet, dry) = (mix * FRAC_PI_2).sin_cos();
+                (dry, wet)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_power_keeps_uncorrelated_power_constant() {
+        // Uncorrelated signals of equal power add up their powers, so only the gains matter
+        for step in 0..=100 {
+            let (dry, wet) = MixLaw::EqualPower.gains(step as f32 / 100.0);
+            let power = dry * dry + wet * wet;
+            assert!((power - 1.0).abs() < 1e-5, "power {power} at {step}%");
+        }
+    }
+
+    #[test]
+    fn linear_dips_at_the_midpoint() {
+        let (dry, wet) = MixLaw::Linear.gains(0.5);
+
+        assert!((dry * dry + wet * wet - 0.5).abs() < 1e-6);
+    }
+}
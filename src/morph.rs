@@ -0,0 +1,75 @@
+use nih_plug::params::persist::PersistentField;
+use nih_plug::prelude::{ParamFlags, ParamPtr, Params};
+use std::sync::RwLock;
+
+use crate::settings;
+
+/// Two stored snapshots of the parameters that the editor's morph slider blends between. The
+/// snapshots use the same format as the copied settings, and they're persisted with the plugin's
+/// state.
+#[derive(Debug, Default)]
+pub struct MorphSlots {
+    pub a: RwLock<String>,
+    pub b: RwLock<String>,
+}
+
+impl<'a> PersistentField<'a, (String, String)> for MorphSlots {
+    fn set(&self, (a, b): (String, String)) {
+        *self.a.write().unwrap() = a;
+        *self.b.write().unwrap() = b;
+    }
+
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&(String, String)) -> R,
+    {
+        f(&(self.a.read().unwrap().clone(), self.b.read().unwrap().clone()))
+    }
+}
+
+impl MorphSlots {
+    /// Store the current parameter values in slot A or B.
+    pub fn store(&self, params: &dyn Params, slot_b: bool) {
+        let slot = if slot_b { &self.b } else { &self.a };
+        *slot.write().unwrap() = settings::export_settings(params);
+    }
+
+    /// Whether both slots have been filled, which is needed for morphing.
+    pub fn is_ready(&self) -> bool {
+        !self.a.read().unwrap().is_empty() && !self.b.read().unwrap().is_empty()
+    }
+
+    /// The normalized parameter values at `morph` between slot A at 0 and slot B at 1. Continuous
+    /// parameters are interpolated in their normalized domain, which for the decibel and
+    /// frequency parameters means they move evenly on their own scales. Stepped parameters like
+    /// the enums and toggles snap over at the halfway point. Non-automatable parameters are left
+    /// out. Those include everything that changes the latency.
+    pub fn morph(&self, params: &dyn Params, morph: f32) -> Vec<(ParamPtr, f32)> {
+        let slot_a = settings::parse_settings(params, &self.a.read().unwrap()).unwrap_or_default();
+        let slot_b = settings::parse_settings(params, &self.b.read().unwrap()).unwrap_or_default();
+
+        slot_a
+            .into_iter()
+            // SAFETY: The pointers come from `params`, which outlives this function
+            .filter(|(param_ptr, _)| {
+                !unsafe { param_ptr.flags() }.contains(ParamFlags::NON_AUTOMATABLE)
+            })
+            .filter_map(|(param_ptr, a)| {
+                let (_, b) = slot_b.iter().find(|(other_ptr, _)| *other_ptr == param_ptr)?;
+
+                // SAFETY: The pointers come from `params`, which outlives this function
+                let value = if unsafe { param_ptr.step_count() }.is_some() {
+                    if morph < 0.5 {
+                        a
+                    } else {
+                        *b
+                    }
+                } else {
+                    a + (b - a) * morph
+                };
+
+                Some((param_ptr, value))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,72 @@
+use nih_plug::prelude::Enum;
+use std::f32::consts::TAU;
+
+/// The mains frequency used for the hum component of the analog noise.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HumFrequency {
+    Off,
+    #[name = "50 Hz"]
+    Hz50,
+    #[name = "60 Hz"]
+    Hz60,
+}
+
+impl HumFrequency {
+    pub fn frequency(self) -> Option<f32> {
+        match self {
+            HumFrequency::Off => None,
+            HumFrequency::Hz50 => Some(50.0),
+            HumFrequency::Hz60 => Some(60.0),
+        }
+    }
+}
+
+/// A cheap xorshift based white noise generator. Every channel gets its own generator so the
+/// noise is decorrelated between channels while still being deterministic for a given seed.
+#[derive(Debug, Clone)]
+pub struct NoiseGenerator {
+    state: u32,
+}
+
+impl NoiseGenerator {
+    pub fn new(seed: u32) -> Self {
+        // Xorshift gets stuck at zero
+        Self { state: seed.max(1) }
+    }
+
+    /// Returns a uniformly distributed sample in `[-1, 1]`.
+    pub fn next(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+
+        (self.state as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+}
+
+/// Mains hum with a couple of its harmonics, which sounds a lot more like a real ground loop than
+/// a pure sine wave.
+#[derive(Debug, Default, Clone)]
+pub struct HumGenerator {
+    phase: f32,
+}
+
+impl HumGenerator {
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+    }
+
+    /// Returns the next hum sample, roughly normalized to `[-1, 1]`.
+    pub fn next(&mut self, frequency: f32, sample_rate: f32) -> f32 {
+        let sample =
+            (self.phase.sin() + 0.5 * (self.phase * 2.0).sin() + 0.25 * (self.phase * 3.0).sin())
+                / 1.75;
+
+        self.phase += TAU * frequency / sample_rate;
+        if self.phase >= TAU {
+            self.phase -= TAU;
+        }
+
+        sample
+    }
+}
@@ -0,0 +1,156 @@
+use nih_plug::prelude::Enum;
+
+use crate::biquad::{Biquad, BiquadCoefficients};
+
+/// The lowest cutoff the EQ filters go down to. A low cut at this frequency is switched off.
+pub const EQ_MIN_HZ: f32 = 20.0;
+/// The highest cutoff the EQ filters go up to. A high cut at this frequency is switched off.
+pub const EQ_MAX_HZ: f32 = 20_000.0;
+/// The Q values for the two sections of a fourth order Butterworth filter.
+const FOURTH_ORDER_QS: [f64; 2] = [0.541_196_1, 1.306_563];
+
+/// How steeply the low cut filter rolls off.
+#[derive(Enum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LowCutSlope {
+    #[name = "6 dB/oct"]
+    Gentle,
+    #[name = "12 dB/oct"]
+    #[default]
+    Medium,
+    #[name = "24 dB/oct"]
+    Steep,
+}
+
+/// A low cut and high cut filter pair for shaping the wet or dry path of a single channel. At
+/// the ends of their ranges the filters are switched off so a flat EQ is completely transparent.
+#[derive(Debug, Default, Clone)]
+pub struct PathEq {
+    /// The low cut uses a second section for the steepest slope.
+    low_cut: [Biquad; 2],
+    high_cut: Biquad,
+
+    /// The cutoff frequencies and the sample rate the filters were last configured with.
+    settings: (f32, f32),
+    sample_rate: f32,
+    low_cut_slope: LowCutSlope,
+}
+
+impl PathEq {
+    /// Update the cutoff frequencies. The coefficients are only recomputed when they or the
+    /// sample rate change.
+    pub fn set_cutoffs(&mut self, sample_rate: f32, low_cut_hz: f32, high_cut_hz: f32) {
+        let sample_rate_changed = sample_rate != self.sample_rate;
+        if !sample_rate_changed && (low_cut_hz, high_cut_hz) == self.settings {
+            return;
+        }
+
+        if sample_rate_changed || low_cut_hz != self.settings.0 {
+            self.update_low_cut(sample_rate, low_cut_hz);
+        }
+        if sample_rate_changed || high_cut_hz != self.settings.1 {
+            self.high_cut
+                .set_coefficients(BiquadCoefficients::lowpass(sample_rate, high_cut_hz));
+        }
+        self.settings = (low_cut_hz, high_cut_hz);
+        self.sample_rate = sample_rate;
+    }
+
+    /// Change the low cut's slope. This defaults to 12 dB per octave.
+    pub fn set_low_cut_slope(&mut self, sample_rate: f32, slope: LowCutSlope) {
+        if slope != self.low_cut_slope {
+            self.low_cut_slope = slope;
+            self.update_low_cut(sample_rate, self.settings.0);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for filter in &mut self.low_cut {
+            filter.reset();
+        }
+        self.high_cut.reset();
+    }
+
+    /// Filter a sample. The filters keep running while they're switched off, so their state has
+    /// already settled by the time they get switched on.
+    pub fn process(&mut self, input: f32, double_precision: bool) -> f32 {
+        let (low_cut_hz, high_cut_hz) = self.settings;
+
+        let [first_section, second_section] = &mut self.low_cut;
+        let low_cut = first_section.process(input, double_precision);
+        let steep_low_cut = second_section.process(low_cut, double_precision);
+        let output = if low_cut_hz <= EQ_MIN_HZ {
+            input
+        } else if self.low_cut_slope == LowCutSlope::Steep {
+            steep_low_cut
+        } else {
+            low_cut
+        };
+        let high_cut = self.high_cut.process(output, double_precision);
+        if high_cut_hz < EQ_MAX_HZ {
+            high_cut
+        } else {
+            output
+        }
+    }
+
+    fn update_low_cut(&mut self, sample_rate: f32, low_cut_hz: f32) {
+        let [first_section, second_section] = &mut self.low_cut;
+        match self.low_cut_slope {
+            LowCutSlope::Gentle => first_section.set_coefficients(
+                BiquadCoefficients::highpass_first_order(sample_rate, low_cut_hz),
+            ),
+            LowCutSlope::Medium => first_section
+                .set_coefficients(BiquadCoefficients::highpass(sample_rate, low_cut_hz)),
+            LowCutSlope::Steep => {
+                let [first_q, second_q] = FOURTH_ORDER_QS;
+                first_section.set_coefficients(BiquadCoefficients::highpass_with_q(
+                    sample_rate,
+                    low_cut_hz,
+                    first_q,
+                ));
+                second_section.set_coefficients(BiquadCoefficients::highpass_with_q(
+                    sample_rate,
+                    low_cut_hz,
+                    second_q,
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_1_SQRT_2, TAU};
+
+    /// The gain of a settled sine at `frequency` through the EQ.
+    fn sine_gain(eq: &mut PathEq, sample_rate: f32, frequency: f32) -> f32 {
+        eq.reset();
+        let num_samples = sample_rate as usize;
+        (0..num_samples)
+            .map(|i| eq.process((TAU * frequency * i as f32 / sample_rate).sin(), true))
+            .skip(num_samples / 2)
+            .fold(0.0, |peak, sample| peak.max(sample.abs()))
+    }
+
+    #[test]
+    fn cutoffs_follow_a_sample_rate_change() {
+        // The cutoffs stay the same, so only the sample rate change can update the coefficients
+        let (mut high_cut, mut low_cut) = (PathEq::default(), PathEq::default());
+        for sample_rate in [44_100.0, 96_000.0, 48_000.0] {
+            high_cut.set_cutoffs(sample_rate, EQ_MIN_HZ, 1000.0);
+            let high_cut_gain = sine_gain(&mut high_cut, sample_rate, 1000.0);
+            assert!(
+                (high_cut_gain - FRAC_1_SQRT_2).abs() < 0.01,
+                "high cut gain {high_cut_gain} at {sample_rate} Hz"
+            );
+
+            low_cut.set_cutoffs(sample_rate, 1000.0, EQ_MAX_HZ);
+            let low_cut_gain = sine_gain(&mut low_cut, sample_rate, 1000.0);
+            assert!(
+                (low_cut_gain - FRAC_1_SQRT_2).abs() < 0.01,
+                "low cut gain {low_cut_gain} at {sample_rate} Hz"
+            );
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use nih_plug::prelude::{NoteEvent, Smoother, SmoothingStyle};
+
+/// The CLAP polyphonic modulation ID for the drive parameter.
+pub const DRIVE_POLY_MOD_ID: u32 = 0;
+/// The number of voices whose drive modulation can be tracked at the same time.
+pub const MAX_VOICES: u32 = 16;
+
+/// Tracks per-voice CLAP polyphonic modulation of the drive parameter.
+///
+/// This is an effect, so there is only a single audio stream rather than one per voice. When a host
+/// sends polyphonic modulation through the effect (for instance when it sits in an instrument's
+/// routing chain), the offsets of all voices that are currently playing are averaged and applied
+/// to that single stream. When no voice is being modulated, the drive parameter's regular
+/// (monophonic) value is used, so hosts without polyphonic modulation behave exactly as before.
+#[derive(Debug)]
+pub struct PolyDriveModulation {
+    /// The normalized drive offset for every active voice, keyed by voice ID.
+    voices: [Option<(i32, f32)>; MAX_VOICES as usize],
+    /// Smooths the averaged normalized offset so voices starting and stopping don't click.
+    offset: Smoother<f32>,
+}
+
+impl Default for PolyDriveModulation {
+    fn default() -> Self {
+        Self {
+            voices: [None; MAX_VOICES as usize],
+            offset: Smoother::new(SmoothingStyle::Linear(10.0)),
+        }
+    }
+}
+
+impl PolyDriveModulation {
+    /// Jump the smoothed offset straight to the active voices' average. The voices themselves are
+    /// only removed by their note events, so resetting the DSP doesn't drop the modulation of
+    /// voices that are still playing.
+    pub fn reset(&mut self) {
+        self.offset.reset(self.mean_offset());
+    }
+
+    /// Update the voices from a note event. Events that don't concern the drive's modulation are
+    /// ignored.
+    pub fn handle_event<S>(&mut self, event: &NoteEvent<S>, sample_rate: f32) {
+        match *event {
+            NoteEvent::PolyModulation {
+                voice_id,
+                poly_modulation_id: DRIVE_POLY_MOD_ID,
+                normalized_offset,
+                ..
+            } => self.set_voice(voice_id, normalized_offset),
+            NoteEvent::NoteOff {
+                voice_id: Some(voice_id),
+                ..
+            }
+            | NoteEvent::Choke {
+                voice_id: Some(voice_id),
+                ..
+            }
+            | NoteEvent::VoiceTerminated {
+                voice_id: Some(voice_id),
+                ..
+            } => self.remove_voice(voice_id),
+            _ => return,
+        }
+
+        self.offset.set_target(sample_rate, self.mean_offset());
+    }
+
+    /// The smoothed normalized offset for the current sample. This is zero when no voice is being
+    /// modulated.
+    pub fn next_offset(&self) -> f32 {
+        self.offset.next()
+    }
+
+    fn set_voice(&mut self, voice_id: i32, normalized_offset: f32) {
+        if let Some((_, offset)) = self
+            .voices
+            .iter_mut()
+            .flatten()
+            .find(|(id, _)| *id == voice_id)
+        {
+            *offset = normalized_offset;
+        } else if let Some(slot) = self.voices.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some((voice_id, normalized_offset));
+        }
+        // If every slot is taken the new voice's modulation is simply dropped
+    }
+
+    fn remove_voice(&mut self, voice_id: i32) {
+        for slot in &mut self.voices {
+            if matches!(slot, Some((id, _)) if *id == voice_id) {
+                *slot = None;
+            }
+        }
+    }
+
+    fn mean_offset(&self) -> f32 {
+        let (sum, count) = self
+            .voices
+            .iter()
+            .flatten()
+            .fold((0.0, 0), |(sum, count), (_, offset)| {
+                (sum + offset, count + 1)
+            });
+
+        if count == 0 {
+            0.0
+        } else {
+            sum / count as f32
+        }
+    }
+}
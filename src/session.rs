@@ -0,0 +1,36 @@
+use atomic_float::AtomicF32;
+use nih_plug::prelude::BufferConfig;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// The host's current sample rate and maximum block size, shown in the editor for
+/// troubleshooting. These are updated every time the plugin is initialized.
+#[derive(Debug, Default)]
+pub struct SessionInfo {
+    sample_rate: AtomicF32,
+    max_buffer_size: AtomicU32,
+}
+
+impl SessionInfo {
+    /// Called from `initialize()`.
+    pub fn update(&self, buffer_config: &BufferConfig) {
+        self.sample_rate
+            .store(buffer_config.sample_rate, Ordering::Relaxed);
+        self.max_buffer_size
+            .store(buffer_config.max_buffer_size, Ordering::Relaxed);
+    }
+
+    /// A short description like `48 kHz, 512 samples`, or an empty string before the plugin has
+    /// been initialized.
+    pub fn description(&self) -> String {
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed);
+        if sample_rate <= 0.0 {
+            return String::new();
+        }
+
+        format!(
+            "{} kHz, {} samples",
+            sample_rate / 1000.0,
+            self.max_buffer_size.load(Ordering::Relaxed)
+        )
+    }
+}
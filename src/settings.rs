@@ -0,0 +1,44 @@
+use nih_plug::prelude::{ParamPtr, Params};
+
+/// Every settings string starts with this, so random clipboard contents are rejected and the
+/// format can be changed later without misreading old strings.
+const SETTINGS_PREFIX: &str = "distortion-v1:";
+
+/// Serialize the current parameter values to a compact string that can be shared as text. The
+/// values are stored normalized, as `id=value` pairs separated by semicolons.
+pub fn export_settings(params: &dyn Params) -> String {
+    let values: Vec<String> = params
+        .param_map()
+        .into_iter()
+        .map(|(id, param_ptr, _)| {
+            // SAFETY: The pointers come from `params`, which outlives this function
+            let value = unsafe { param_ptr.unmodulated_normalized_value() };
+            format!("{id}={value}")
+        })
+        .collect();
+
+    format!("{SETTINGS_PREFIX}{}", values.join(";"))
+}
+
+/// Parse a string created by [`export_settings()`] into the normalized values that should be
+/// applied to `params`. Returns `None` if the string is malformed. Parameters that don't exist in
+/// this version of the plugin are skipped, and parameters missing from the string are left alone.
+pub fn parse_settings(params: &dyn Params, text: &str) -> Option<Vec<(ParamPtr, f32)>> {
+    let pairs = text.trim().strip_prefix(SETTINGS_PREFIX)?;
+    let param_map = params.param_map();
+
+    let mut values = Vec::new();
+    for pair in pairs.split(';').filter(|pair| !pair.is_empty()) {
+        let (id, value) = pair.split_once('=')?;
+        let value: f32 = value.parse().ok()?;
+        if !(0.0..=1.0).contains(&value) {
+            return None;
+        }
+
+        if let Some((_, param_ptr, _)) = param_map.iter().find(|(param_id, _, _)| param_id == id) {
+            values.push((*param_ptr, value));
+        }
+    }
+
+    Some(values)
+}
@@ -0,0 +1,83 @@
+use nih_plug::prelude::util;
+
+use crate::biquad::{Biquad, BiquadCoefficients};
+use crate::envelope::EnvelopeFollower;
+use crate::knee_clip;
+
+/// How much lower the high band's threshold gets at full sensitivity during pure sibilance.
+const MAX_THRESHOLD_REDUCTION_DB: f32 = 18.0;
+/// The detector's envelope times. The attack is fast enough to catch an 's' as it starts.
+const DETECTOR_ATTACK_MS: f32 = 1.0;
+const DETECTOR_RELEASE_MS: f32 = 50.0;
+
+/// De-esser style clipping for vocals. A high-pass filter splits off the sibilance region, and
+/// while that band makes up a large part of the signal it's clipped again at a lower threshold.
+/// This tames harsh 's' and 't' sounds that the distortion would otherwise make even harsher,
+/// while the rest of the signal is clipped as usual.
+#[derive(Debug, Default, Clone)]
+pub struct SibilanceClipper {
+    filter: Biquad,
+    /// The frequency the filter was last configured with.
+    frequency: f32,
+
+    band_envelope: EnvelopeFollower,
+    full_envelope: EnvelopeFollower,
+}
+
+impl SibilanceClipper {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            band_envelope: EnvelopeFollower::new(
+                sample_rate,
+                DETECTOR_ATTACK_MS,
+                DETECTOR_RELEASE_MS,
+            ),
+            full_envelope: EnvelopeFollower::new(
+                sample_rate,
+                DETECTOR_ATTACK_MS,
+                DETECTOR_RELEASE_MS,
+            ),
+            ..Self::default()
+        }
+    }
+
+    /// Update the high-pass filter's cutoff. The coefficients are only recomputed when it changes.
+    pub fn set_frequency(&mut self, sample_rate: f32, frequency: f32) {
+        if frequency != self.frequency {
+            self.filter
+                .set_coefficients(BiquadCoefficients::highpass(sample_rate, frequency));
+            self.frequency = frequency;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.filter.reset();
+        self.band_envelope.reset();
+        self.full_envelope.reset();
+    }
+
+    /// Clip the sibilance in an already clipped signal. The filter and detector keep running at
+    /// zero sensitivity so they've settled by the time it's turned up, but the signal is passed
+    /// through untouched.
+    pub fn process(
+        &mut self,
+        input: f32,
+        threshold: f32,
+        knee: f32,
+        sensitivity: f32,
+        double_precision: bool,
+    ) -> f32 {
+        let band = self.filter.process(input, double_precision);
+        let band_envelope = self.band_envelope.process(band);
+        let full_envelope = self.full_envelope.process(input);
+        if sensitivity <= 0.0 || full_envelope <= f32::EPSILON {
+            return input;
+        }
+
+        let sibilance = (band_envelope / full_envelope).min(1.0);
+        let band_threshold =
+            threshold * util::db_to_gain(-MAX_THRESHOLD_REDUCTION_DB * sensitivity * sibilance);
+
+        input - band + knee_clip(band, band_threshold, knee)
+    }
+}
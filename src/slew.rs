@@ -0,0 +1,61 @@
+/// Op-amp style slew rate limiting. The output follows the input, but it can only move by a
+/// limited amount per sample, so fast edges turn into ramps and loud high frequencies turn into
+/// triangle waves. Unlike the clipper this depends on how fast the signal changes rather than on
+/// its level.
+#[derive(Debug, Default, Clone)]
+pub struct SlewLimiter {
+    previous_output: f32,
+}
+
+impl SlewLimiter {
+    pub fn reset(&mut self) {
+        self.previous_output = 0.0;
+    }
+
+    /// Limit the signal to `slew_rate` in full scale per millisecond. When `enabled` is false the
+    /// input is passed through, but the limiter still follows it so enabling it doesn't jump.
+    pub fn process(&mut self, input: f32, slew_rate: f32, sample_rate: f32, enabled: bool) -> f32 {
+        let output = if enabled {
+            let max_step = slew_rate * 1000.0 / sample_rate;
+            self.previous_output + (input - self.previous_output).clamp(-max_step, max_step)
+        } else {
+            input
+        };
+        self.previous_output = output;
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_wave_slope_is_bounded() {
+        let (slew_rate, sample_rate) = (0.5, 48_000.0);
+        let max_step = slew_rate * 1000.0 / sample_rate;
+        let mut slew_limiter = SlewLimiter::default();
+
+        let mut previous = 0.0;
+        let mut peak = 0.0f32;
+        for i in 0..4800 {
+            let square = if (i / 240) % 2 == 0 { 1.0 } else { -1.0 };
+            let output = slew_limiter.process(square, slew_rate, sample_rate, true);
+            assert!((output - previous).abs() <= max_step + 1e-6, "sample {i}");
+            previous = output;
+            peak = peak.max(output.abs());
+        }
+
+        // The edges are slow enough for the ramps to still reach the full level
+        assert!((peak - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn disabled_limiter_passes_the_input() {
+        let mut slew_limiter = SlewLimiter::default();
+
+        assert_eq!(slew_limiter.process(1.0, 0.01, 48_000.0, false), 1.0);
+        assert_eq!(slew_limiter.process(-1.0, 0.01, 48_000.0, false), -1.0);
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::envelope;
+
+/// How long the mid/side energies are averaged over before deciding anything.
+const ANALYSIS_TIME_MS: f32 = 300.0;
+/// Mean square levels below this (about -60 dBFS) are treated as silence and never trigger a
+/// warning.
+const SILENCE_POWER: f32 = 1e-6;
+/// Input material with less side energy than this relative to the mid is considered mono to begin
+/// with, so it can't collapse any further.
+const MIN_SOURCE_WIDTH: f32 = 0.01;
+/// The output is flagged as collapsed when its side-to-mid ratio drops below this fraction of the
+/// input's ratio.
+const COLLAPSE_RATIO: f32 = 0.25;
+
+/// Flags shared with the editor describing problems with the output's stereo image.
+#[derive(Debug, Default)]
+pub struct StereoWarnings {
+    /// The output is a lot narrower than the input.
+    pub collapsed: AtomicBool,
+    /// The output contains more side than mid energy, which means L/R are mostly out of phase.
+    pub out_of_phase: AtomicBool,
+}
+
+impl StereoWarnings {
+    pub fn message(&self) -> &'static str {
+        if self.out_of_phase.load(Ordering::Relaxed) {
+            "Stereo image is out of phase"
+        } else if self.collapsed.load(Ordering::Relaxed) {
+            "Stereo image collapsed to mono"
+        } else {
+            ""
+        }
+    }
+}
+
+/// Tracks the mid and side energy of both the input and the output to detect when processing has
+/// narrowed or inverted the stereo image.
+#[derive(Debug, Default)]
+pub struct StereoAnalyzer {
+    coeff: f32,
+
+    input_mid: f32,
+    input_side: f32,
+    output_mid: f32,
+    output_side: f32,
+}
+
+impl StereoAnalyzer {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            coeff: envelope::time_constant(sample_rate, ANALYSIS_TIME_MS),
+            ..Self::default()
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.input_mid = 0.0;
+        self.input_side = 0.0;
+        self.output_mid = 0.0;
+        self.output_side = 0.0;
+    }
+
+    /// Update the running energies with a single stereo input and output frame.
+    pub fn process(&mut self, input: (f32, f32), output: (f32, f32)) {
+        let (input_mid, input_side) = mid_side_power(input);
+        let (output_mid, output_side) = mid_side_power(output);
+
+        self.input_mid = input_mid + self.coeff * (self.input_mid - input_mid);
+        self.input_side = input_side + self.coeff * (self.input_side - input_side);
+        self.output_mid = output_mid + self.coeff * (self.output_mid - output_mid);
+        self.output_side = output_side + self.coeff * (self.output_side - output_side);
+    }
+
+    pub fn update_warnings(&self, warnings: &StereoWarnings) {
+        let output_power = self.output_mid + self.output_side;
+        let audible = output_power > SILENCE_POWER;
+
+        let out_of_phase = audible && self.output_side > self.output_mid;
+
+        let input_width = self.input_side / self.input_mid.max(SILENCE_POWER);
+        let output_width = self.output_side / self.output_mid.max(SILENCE_POWER);
+        let collapsed = audible
+            && input_width > MIN_SOURCE_WIDTH
+            && output_width < input_width * COLLAPSE_RATIO;
+
+        warnings.out_of_phase.store(out_of_phase, Ordering::Relaxed);
+        warnings.collapsed.store(collapsed, Ordering::Relaxed);
+    }
+}
+
+fn mid_side_power((left, right): (f32, f32)) -> (f32, f32) {
+    let mid = (left + right) * 0.5;
+    let side = (left - right) * 0.5;
+
+    (mid * mid, side * side)
+}
@@ -0,0 +1,172 @@
+use nih_plug::prelude::Enum;
+use std::f32::consts::TAU;
+
+/// The largest delay swing for the slow wow modulation at full depth.
+const WOW_MAX_MS: f32 = 5.0;
+/// The largest delay swing for the faster flutter modulation at full depth.
+const FLUTTER_MAX_MS: f32 = 0.5;
+
+/// How the delay line is read between samples.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DelayInterpolation {
+    /// Cheap, but dulls the highs a little while the delay is modulated.
+    Linear,
+    /// Four-point Hermite interpolation, which keeps more of the highs for a bit more CPU.
+    #[name = "Cubic"]
+    Hermite,
+}
+
+/// Tape-style pitch wobble. The output is read from a delay line whose delay time is modulated by
+/// a slow wow and a faster flutter LFO, which bends the pitch up and down. Both LFOs are shared
+/// between the channels like on a real tape machine.
+///
+/// At zero depth the delay is zero samples and the signal passes through unchanged. The average
+/// delay moves with the depth, so it isn't reported as latency.
+#[derive(Debug, Default)]
+pub struct WowFlutter {
+    /// One delay line per channel.
+    delay_lines: Vec<Vec<f32>>,
+    write_pos: usize,
+
+    wow_phase: f32,
+    flutter_phase: f32,
+}
+
+impl WowFlutter {
+    /// Allocate the delay lines for the largest possible delay. This should be called from
+    /// `initialize()`.
+    pub fn new(num_channels: usize, sample_rate: f32) -> Self {
+        let max_delay = ((WOW_MAX_MS + FLUTTER_MAX_MS) / 1000.0 * sample_rate).ceil() as usize;
+
+        Self {
+            // The cubic interpolation needs two extra samples past the longest delay
+            delay_lines: vec![vec![0.0; max_delay + 3]; num_channels],
+            ..Self::default()
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for delay_line in &mut self.delay_lines {
+            delay_line.fill(0.0);
+        }
+        self.write_pos = 0;
+        self.wow_phase = 0.0;
+        self.flutter_phase = 0.0;
+    }
+
+    /// Process a single frame. The depths range from 0 to 1 and the rates are in Hertz.
+    pub fn process<'a>(
+        &mut self,
+        frame: impl Iterator<Item = &'a mut f32>,
+        sample_rate: f32,
+        (wow_rate, wow_depth): (f32, f32),
+        (flutter_rate, flutter_depth): (f32, f32),
+        interpolation: DelayInterpolation,
+    ) {
+        let delay_len = match self.delay_lines.first() {
+            Some(delay_line) => delay_line.len(),
+            None => return,
+        };
+
+        // Both LFOs swing between zero and their maximum delay so the delay never goes negative
+        let wow = (1.0 + self.wow_phase.sin()) * 0.5 * wow_depth * WOW_MAX_MS;
+        let flutter = (1.0 + self.flutter_phase.sin()) * 0.5 * flutter_depth * FLUTTER_MAX_MS;
+        self.wow_phase = (self.wow_phase + TAU * wow_rate / sample_rate) % TAU;
+        self.flutter_phase = (self.flutter_phase + TAU * flutter_rate / sample_rate) % TAU;
+
+        let delay = ((wow + flutter) / 1000.0 * sample_rate).min((delay_len - 3) as f32);
+        let delay_samples = delay.floor() as usize;
+        let frac = delay - delay_samples as f32;
+        let newer_pos = (self.write_pos + delay_len - delay_samples) % delay_len;
+        let older_pos = (newer_pos + delay_len - 1) % delay_len;
+        // The sample just written is the newest one there is, so it also stands in for the point
+        // before it when there's no delay
+        let newest_pos = if delay_samples == 0 {
+            newer_pos
+        } else {
+            (newer_pos + 1) % delay_len
+        };
+        let oldest_pos = (older_pos + delay_len - 1) % delay_len;
+
+        for (sample, delay_line) in frame.zip(&mut self.delay_lines) {
+            delay_line[self.write_pos] = *sample;
+            *sample = match interpolation {
+                DelayInterpolation::Linear => {
+                    delay_line[newer_pos] + (delay_line[older_pos] - delay_line[newer_pos]) * frac
+                }
+                DelayInterpolation::Hermite => hermite(
+                    [
+                        delay_line[newest_pos],
+                        delay_line[newer_pos],
+                        delay_line[older_pos],
+                        delay_line[oldest_pos],
+                    ],
+                    frac,
+                ),
+            };
+        }
+
+        self.write_pos = (self.write_pos + 1) % delay_len;
+    }
+}
+
+/// Four-point, third-order Hermite interpolation between `points[1]` and `points[2]`, with `frac`
+/// going from 0 to 1.
+fn hermite([y0, y1, y2, y3]: [f32; 4], frac: f32) -> f32 {
+    let c1 = 0.5 * (y2 - y0);
+    let c2 = y0 - 2.5 * y1 + 2.0 * y2 - 0.5 * y3;
+    let c3 = 0.5 * (y3 - y0) + 1.5 * (y1 - y2);
+
+    ((c3 * frac + c2) * frac + c1) * frac + y1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The RMS error of a sine read through a delay that's swept by the wow LFO, compared to the
+    /// exact delayed sine.
+    fn swept_delay_error(interpolation: DelayInterpolation) -> f32 {
+        let (sample_rate, frequency) = (48_000.0, 5000.0);
+        let (wow_rate, wow_depth) = (2.0, 1.0);
+        let sine = |time: f64| (std::f64::consts::TAU * frequency * time / sample_rate).sin();
+        let mut wow_flutter = WowFlutter::new(1, sample_rate as f32);
+
+        let mut wow_phase = 0.0f32;
+        let mut squared_error = 0.0;
+        let num_samples = 48_000;
+        for i in 0..num_samples {
+            // The same delay the LFO produces for this sample
+            let delay = (1.0 + wow_phase.sin()) * 0.5 * wow_depth * WOW_MAX_MS / 1000.0;
+            let delay = delay as f64 * sample_rate;
+            wow_phase = (wow_phase + TAU * wow_rate / sample_rate as f32) % TAU;
+
+            let mut sample = sine(i as f64) as f32;
+            wow_flutter.process(
+                std::iter::once(&mut sample),
+                sample_rate as f32,
+                (wow_rate, wow_depth),
+                (0.0, 0.0),
+                interpolation,
+            );
+            // The delay line starts out empty
+            if i >= 1000 {
+                let error = sample as f64 - sine(i as f64 - delay);
+                squared_error += error * error;
+            }
+        }
+
+        (squared_error / (num_samples - 1000) as f64).sqrt() as f32
+    }
+
+    #[test]
+    fn hermite_is_more_accurate_than_linear() {
+        let linear_error = swept_delay_error(DelayInterpolation::Linear);
+        let hermite_error = swept_delay_error(DelayInterpolation::Hermite);
+
+        assert!(
+            hermite_error < linear_error / 3.0,
+            "Hermite {hermite_error}, linear {linear_error}"
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use nih_plug::prelude::{util, Enum};
+
+/// The top of the drive parameter's range in decibels. The tapers reshape the range without
+/// changing its ends.
+pub const DRIVE_MAX_DB: f32 = 36.0;
+
+/// How the drive knob's travel maps to the actual drive gain.
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriveTaper {
+    /// Even steps in decibels across the whole knob.
+    Linear,
+    /// Gentle at the bottom and steep at the top, for fine control over light drive.
+    Exponential,
+    /// Gentle at both ends with most of the change in the middle of the knob.
+    #[name = "S-Curve"]
+    SCurve,
+}
+
+impl DriveTaper {
+    /// Reshape a drive gain from the drive parameter to the gain that should be applied.
+    pub fn apply(self, drive: f32) -> f32 {
+        let position = (util::gain_to_db(drive) / DRIVE_MAX_DB).clamp(0.0, 1.0);
+        let position = match self {
+            DriveTaper::Linear => return drive,
+            DriveTaper::Exponential => position * position,
+            DriveTaper::SCurve => position * position * (3.0 - 2.0 * position),
+        };
+
+        util::db_to_gain(position * DRIVE_MAX_DB)
+    }
+}
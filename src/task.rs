@@ -0,0 +1,155 @@
+use nih_plug::nih_log;
+use nih_plug::prelude::util;
+
+/// How often the meter log writes a snapshot while it's enabled.
+const METER_LOG_INTERVAL_SECS: f32 = 1.0;
+
+/// Work the audio thread hands off to nih-plug's background thread. Posting a task doesn't block
+/// or allocate, so anything slow happens off the audio thread.
+#[derive(Debug, Clone, Copy)]
+pub enum Task {
+    /// Write a snapshot of the meter readings to the log.
+    LogMeters(MeterSnapshot),
+}
+
+/// The meter readings over one logging interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeterSnapshot {
+    /// The output's highest sample peak.
+    pub output_peak: f32,
+    /// The lowest gain the clipper applied, so the most gain reduction.
+    pub clipper_gain: f32,
+    /// How much the clipper changed the signal relative to the driven signal's level, from 0 to 1.
+    pub distortion: f32,
+}
+
+/// Collects the meter readings on the audio thread and turns them into a snapshot once per
+/// interval. Unlike the editor's meters this also works with the editor closed.
+#[derive(Debug, Default)]
+pub struct MeterLogger {
+    interval_samples: usize,
+    samples: usize,
+
+    output_peak: f32,
+    clipper_gain: f32,
+    driven_power: f32,
+    distortion_power: f32,
+}
+
+impl MeterLogger {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut logger = Self {
+            interval_samples: (METER_LOG_INTERVAL_SECS * sample_rate).round() as usize,
+            ..Self::default()
+        };
+        logger.reset();
+
+        logger
+    }
+
+    pub fn reset(&mut self) {
+        self.samples = 0;
+        self.output_peak = 0.0;
+        self.clipper_gain = 1.0;
+        self.driven_power = 0.0;
+        self.distortion_power = 0.0;
+    }
+
+    /// Add a frame's output peak, clipper gain, and the driven signal's power together with the
+    /// power of what the clipper changed about it. This returns a snapshot at the end of every
+    /// interval.
+    pub fn add_frame(
+        &mut self,
+        output_peak: f32,
+        clipper_gain: f32,
+        (driven_power, distortion_power): (f32, f32),
+    ) -> Option<MeterSnapshot> {
+        self.output_peak = self.output_peak.max(output_peak);
+        self.clipper_gain = self.clipper_gain.min(clipper_gain);
+        self.driven_power += driven_power;
+        self.distortion_power += distortion_power;
+
+        self.samples += 1;
+        if self.samples < self.interval_samples.max(1) {
+            return None;
+        }
+
+        let distortion = if self.driven_power > f32::EPSILON {
+            (self.distortion_power / self.driven_power).sqrt().min(1.0)
+        } else {
+            0.0
+        };
+        let snapshot = MeterSnapshot {
+            output_peak: self.output_peak,
+            clipper_gain: self.clipper_gain,
+            distortion,
+        };
+        self.reset();
+
+        Some(snapshot)
+    }
+}
+
+impl Task {
+    /// The line this task writes to the log.
+    fn log_message(&self) -> String {
+        match self {
+            Task::LogMeters(snapshot) => format!(
+                "Output peak: {:.1} dBFS, gain reduction: {:.1} dB, distortion: {:.0}%",
+                util::gain_to_db(snapshot.output_peak),
+                // The clipper never adds gain, so this doesn't show up as -0.0 dB
+                util::gain_to_db(snapshot.clipper_gain).abs(),
+                snapshot.distortion * 100.0
+            ),
+        }
+    }
+}
+
+/// Run a task on the background thread.
+pub fn execute(task: Task) {
+    nih_log!("{}", task.log_message());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meter_log_writes_one_snapshot_per_interval() {
+        let mut logger = MeterLogger::new(100.0);
+        let mut snapshots = Vec::new();
+        for i in 0..250 {
+            // Half a second of a clipped signal, and then a clean one
+            let (output_peak, clipper_gain, distortion_frame) = if i < 50 {
+                (0.5, 0.5, (1.0, 0.25))
+            } else {
+                (0.25, 1.0, (1.0, 0.0))
+            };
+            snapshots.extend(logger.add_frame(output_peak, clipper_gain, distortion_frame));
+        }
+
+        assert_eq!(
+            snapshots,
+            [
+                MeterSnapshot {
+                    output_peak: 0.5,
+                    clipper_gain: 0.5,
+                    distortion: 0.5f32.sqrt() * 0.5,
+                },
+                MeterSnapshot {
+                    output_peak: 0.25,
+                    clipper_gain: 1.0,
+                    distortion: 0.0,
+                },
+            ]
+        );
+        assert_eq!(
+            Task::LogMeters(snapshots[0]).log_message(),
+            "Output peak: -6.0 dBFS, gain reduction: 6.0 dB, distortion: 35%"
+        );
+        assert_eq!(
+            Task::LogMeters(snapshots[1]).log_message(),
+            "Output peak: -12.0 dBFS, gain reduction: 0.0 dB, distortion: 0%"
+        );
+    }
+}
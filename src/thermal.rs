@@ -0,0 +1,66 @@
+use nih_plug::prelude::util;
+
+use crate::envelope::time_constant;
+
+/// How long the clipper takes to heat up under sustained heavy signal.
+const HEAT_UP_MS: f32 = 3000.0;
+/// How long it takes to cool back down afterwards.
+const COOL_DOWN_MS: f32 = 8000.0;
+/// How far the clipping threshold sags when fully heated at full depth.
+const MAX_THRESHOLD_SAG_DB: f32 = 3.0;
+/// How much of the remaining knee range gets added when fully heated at full depth.
+const MAX_KNEE_SOFTENING: f32 = 0.5;
+
+/// A slowly changing temperature for the clipper, like a component that heats up when it's pushed
+/// hard for a while. The heat follows how far the driven signal goes past the threshold. A hot
+/// clipper starts clipping a bit earlier and more softly, and it takes several seconds to recover.
+#[derive(Debug, Default, Clone)]
+pub struct ThermalState {
+    heat_up_coeff: f32,
+    cool_down_coeff: f32,
+
+    /// The current temperature from 0 to 1.
+    heat: f32,
+}
+
+impl ThermalState {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            heat_up_coeff: time_constant(sample_rate, HEAT_UP_MS),
+            cool_down_coeff: time_constant(sample_rate, COOL_DOWN_MS),
+            heat: 0.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.heat = 0.0;
+    }
+
+    /// The gain for the clip threshold and the new knee for the current temperature. At zero
+    /// depth the gain is 1 and the knee comes back unchanged.
+    pub fn apply(&self, knee: f32, depth: f32) -> (f32, f32) {
+        let amount = self.heat * depth;
+
+        (
+            util::db_to_gain(-MAX_THRESHOLD_SAG_DB * amount),
+            knee + (1.0 - knee) * MAX_KNEE_SOFTENING * amount,
+        )
+    }
+
+    /// Update the temperature with a frame's loudest driven sample and the threshold it was
+    /// clipped at.
+    pub fn add_frame(&mut self, driven_peak: f32, threshold: f32) {
+        let target = if threshold > 0.0 {
+            (driven_peak / threshold - 1.0).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let coeff = if target > self.heat {
+            self.heat_up_coeff
+        } else {
+            self.cool_down_coeff
+        };
+
+        self.heat = target + coeff * (self.heat - target);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::envelope::{time_constant, EnvelopeFollower};
+
+/// The fast envelope tracks the signal closely enough to react to the front of a transient.
+const FAST_ATTACK_MS: f32 = 0.5;
+const FAST_RELEASE_MS: f32 = 20.0;
+/// The slow envelope follows the sustained level the transients are compared against.
+const SLOW_ATTACK_MS: f32 = 20.0;
+const SLOW_RELEASE_MS: f32 = 200.0;
+/// The longest look-ahead for the transient detector. The look-ahead's delay line is sized for
+/// this.
+pub const MAX_TRANSIENT_LOOKAHEAD_MS: f32 = 5.0;
+
+/// Detects transients by comparing a fast and a slow envelope, and turns that into a gate for the
+/// clipper. The gate opens instantly when a transient is detected and closes again over the
+/// release time, so sustained material passes through without being clipped.
+#[derive(Debug, Default, Clone)]
+pub struct TransientDetector {
+    fast: EnvelopeFollower,
+    slow: EnvelopeFollower,
+
+    release_coeff: f32,
+    gate: f32,
+}
+
+impl TransientDetector {
+    pub fn new(sample_rate: f32, release_ms: f32) -> Self {
+        let mut detector = Self {
+            fast: EnvelopeFollower::new(sample_rate, FAST_ATTACK_MS, FAST_RELEASE_MS),
+            slow: EnvelopeFollower::new(sample_rate, SLOW_ATTACK_MS, SLOW_RELEASE_MS),
+            ..Self::default()
+        };
+        detector.set_release(sample_rate, release_ms);
+
+        detector
+    }
+
+    /// Change how long the gate stays open after a transient. This does not reset the detector.
+    pub fn set_release(&mut self, sample_rate: f32, release_ms: f32) {
+        self.release_coeff = time_constant(sample_rate, release_ms);
+    }
+
+    pub fn reset(&mut self) {
+        self.fast.reset();
+        self.slow.reset();
+        self.gate = 0.0;
+    }
+
+    /// Feed a sample into the detector and return how much of the clipped signal should be used,
+    /// from 0 to 1. `sensitivity` ranges from 0 to 1, and at full sensitivity the gate is always
+    /// open.
+    pub fn process(&mut self, input: f32, sensitivity: f32) -> f32 {
+        let fast = self.fast.process(input);
+        let slow = self.slow.process(input);
+
+        // How far the fast envelope rises above the sustained level, from 0 to 1
+        let transient = if fast > f32::EPSILON {
+            (1.0 - slow / fast).max(0.0)
+        } else {
+            0.0
+        };
+        let detection_threshold = (1.0 - sensitivity) * 0.5;
+        let target = if detection_threshold > 0.0 {
+            (transient / detection_threshold).min(1.0)
+        } else {
+            1.0
+        };
+
+        self.gate = if target > self.gate {
+            target
+        } else {
+            target + self.release_coeff * (self.gate - target)
+        };
+
+        self.gate
+    }
+}
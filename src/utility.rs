@@ -0,0 +1,156 @@
+use nih_plug::prelude::{ChannelSamples, Smoother, SmoothingStyle};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{set_stereo_frame, stereo_frame};
+
+/// How long the utilities take to fade in and out, so toggling them doesn't click.
+const UTILITY_FADE_MS: f32 = 10.0;
+/// How long the output fades out before a DSP reset, and back in after it.
+const RESET_FADE_MS: f32 = 5.0;
+
+/// Monitoring utilities toggled from the editor. These are meant for checking the processed
+/// signal, so they aren't parameters and aren't saved with the plugin's state.
+#[derive(Debug, Default)]
+pub struct OutputUtilities {
+    /// Swap the left and right output channels.
+    pub swap_channels: AtomicBool,
+    /// Sum the output to mono.
+    pub mono_sum: AtomicBool,
+    /// Set by the editor to clear all of the DSP state, consumed by the audio thread.
+    reset_requested: AtomicBool,
+}
+
+impl OutputUtilities {
+    pub fn toggle_swap_channels(&self) {
+        self.swap_channels.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    pub fn toggle_mono_sum(&self) {
+        self.mono_sum.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    /// Ask the audio thread to clear all filters, envelopes and delay lines, like reloading the
+    /// plugin would.
+    pub fn request_reset(&self) {
+        self.reset_requested.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Fades the output out when a DSP reset is requested, tells the audio thread when to clear its
+/// state, and then fades back in. This way the reset doesn't click.
+#[derive(Debug)]
+pub struct ResetFade {
+    gain: Smoother<f32>,
+    /// Whether the output is fading out for a reset.
+    fading_out: bool,
+}
+
+impl Default for ResetFade {
+    fn default() -> Self {
+        let gain = Smoother::new(SmoothingStyle::Linear(RESET_FADE_MS));
+        gain.reset(1.0);
+
+        Self {
+            gain,
+            fading_out: false,
+        }
+    }
+}
+
+impl ResetFade {
+    /// Pick up a reset request from the editor. This should be called once per buffer.
+    pub fn update(&mut self, utilities: &OutputUtilities, sample_rate: f32) {
+        if utilities.reset_requested.swap(false, Ordering::Relaxed) && !self.fading_out {
+            self.fading_out = true;
+            self.gain.set_target(sample_rate, 0.0);
+        }
+    }
+
+    /// The output gain for the next frame, and whether the DSP state should be cleared before the
+    /// frame is processed.
+    pub fn next(&mut self, sample_rate: f32) -> (f32, bool) {
+        let gain = self.gain.next();
+        if self.fading_out && !self.gain.is_smoothing() {
+            self.fading_out = false;
+            self.gain.set_target(sample_rate, 1.0);
+            return (0.0, true);
+        }
+
+        (gain, false)
+    }
+}
+
+/// Applies the [`OutputUtilities`] on the audio thread, fading between the states.
+#[derive(Debug)]
+pub struct UtilityProcessor {
+    swap_channels: bool,
+    mono_sum: bool,
+
+    swap_amount: Smoother<f32>,
+    mono_amount: Smoother<f32>,
+}
+
+impl Default for UtilityProcessor {
+    fn default() -> Self {
+        Self {
+            swap_channels: false,
+            mono_sum: false,
+
+            swap_amount: Smoother::new(SmoothingStyle::Linear(UTILITY_FADE_MS)),
+            mono_amount: Smoother::new(SmoothingStyle::Linear(UTILITY_FADE_MS)),
+        }
+    }
+}
+
+impl UtilityProcessor {
+    /// Jump straight to the current state of the toggles.
+    pub fn reset(&mut self, utilities: &OutputUtilities) {
+        self.swap_channels = utilities.swap_channels.load(Ordering::Relaxed);
+        self.mono_sum = utilities.mono_sum.load(Ordering::Relaxed);
+        self.swap_amount.reset(self.swap_channels as u8 as f32);
+        self.mono_amount.reset(self.mono_sum as u8 as f32);
+    }
+
+    /// Pick up changes to the toggles. This should be called once per buffer.
+    pub fn update(&mut self, utilities: &OutputUtilities, sample_rate: f32) {
+        let swap_channels = utilities.swap_channels.load(Ordering::Relaxed);
+        if swap_channels != self.swap_channels {
+            self.swap_channels = swap_channels;
+            self.swap_amount
+                .set_target(sample_rate, swap_channels as u8 as f32);
+        }
+
+        let mono_sum = utilities.mono_sum.load(Ordering::Relaxed);
+        if mono_sum != self.mono_sum {
+            self.mono_sum = mono_sum;
+            self.mono_amount
+                .set_target(sample_rate, mono_sum as u8 as f32);
+        }
+    }
+
+    /// Apply the utilities to a frame. Frames that aren't stereo are left alone.
+    pub fn process(&mut self, channel_samples: &mut ChannelSamples) {
+        let swap_amount = self.swap_amount.next();
+        let mono_amount = self.mono_amount.next();
+        if swap_amount == 0.0 && mono_amount == 0.0 {
+            return;
+        }
+
+        let (left, right) = match stereo_frame(channel_samples) {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let swapped_left = left + (right - left) * swap_amount;
+        let swapped_right = right + (left - right) * swap_amount;
+        let mid = (swapped_left + swapped_right) * 0.5;
+
+        set_stereo_frame(
+            channel_samples,
+            (
+                swapped_left + (mid - swapped_left) * mono_amount,
+                swapped_right + (mid - swapped_right) * mono_amount,
+            ),
+        );
+    }
+}